@@ -0,0 +1,74 @@
+//! Unique named bosses, each waiting on a level of its own and met at most once a run.
+
+use crate::{Map, TileType, gamelog::GameLog, raws, spawner};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::collections::HashSet;
+
+/// Resource holding the names of every boss spawned so far this run.
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct SpawnedBosses {
+    names: HashSet<String>,
+}
+
+impl SpawnedBosses {
+    pub fn new() -> SpawnedBosses {
+        SpawnedBosses {
+            names: HashSet::new(),
+        }
+    }
+
+    /// Check if the named boss has been spawned this run.
+    pub fn has_spawned(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    /// Record the named boss as spawned, so it isn't spawned again this run.
+    pub fn record(&mut self, name: &str) {
+        self.names.insert(name.to_string());
+    }
+}
+
+/// Spawn every boss that waits at this depth and hasn't been met yet this run, guarding the stairs down.
+///
+/// Each boss is announced in the game log.
+pub fn spawn_bosses(ecs: &mut World, depth: i32) {
+    for (name, boss) in raws::raws().bosses_at_depth(depth) {
+        if ecs.fetch::<SpawnedBosses>().has_spawned(name) {
+            continue;
+        }
+
+        let stairs_idx = {
+            let map = ecs.fetch::<Map>();
+            map.tiles
+                .iter()
+                .position(|tile| *tile == TileType::DownStairs)
+        };
+        let Some(stairs_idx) = stairs_idx else {
+            rltk::console::log(format!("No stairs down on level {} for {}", depth, name));
+            continue;
+        };
+        let Some(lair_idx) = spawner::free_tiles_near(ecs, stairs_idx, 1)
+            .first()
+            .copied()
+        else {
+            rltk::console::log(format!(
+                "No room by the stairs on level {} for {}",
+                depth, name
+            ));
+            continue;
+        };
+
+        let map_width = ecs.fetch::<Map>().width;
+        let x = lair_idx as i32 % map_width;
+        let y = lair_idx as i32 / map_width;
+        if raws::spawn_named_entity(ecs, name, x, y).is_none() {
+            continue;
+        }
+
+        ecs.write_resource::<SpawnedBosses>().record(name);
+        ecs.write_resource::<GameLog>()
+            .entries
+            .push(boss.announcement.clone());
+    }
+}
@@ -0,0 +1,73 @@
+//! Logic for peaceful NPCs wandering about.
+
+use crate::{
+    Map, Position, RunState, TileType,
+    components::{Bystander, EntityMoved},
+};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+/// System for bystander NPC AI for an ECS.
+///
+/// Bystanders ignore the player and amble around at random, staying on safe ground.
+pub struct BystanderAI {}
+
+impl<'a> System<'a> for BystanderAI {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        Entities<'a>,
+        ReadStorage<'a, Bystander>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, EntityMoved>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, runstate, mut rng, entities, bystanders, mut positions, mut entity_moved) =
+            data;
+
+        // Only run if it's monster's turn.
+        if *runstate != RunState::MonsterTurn {
+            return;
+        }
+
+        for (entity, _bystander, pos) in (&entities, &bystanders, &mut positions).join() {
+            // Pick a direction, or stand still for a turn.
+            let (delta_x, delta_y) = match rng.roll_dice(1, 5) {
+                1 => (-1, 0),
+                2 => (1, 0),
+                3 => (0, -1),
+                4 => (0, 1),
+                _ => continue,
+            };
+
+            let x = pos.x + delta_x;
+            let y = pos.y + delta_y;
+            if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 {
+                continue;
+            }
+
+            let destination_idx = map.xy_idx(x, y);
+            let tile = map.tiles[destination_idx];
+            // Bystanders don't open doors, so they stay out of buildings.
+            if map.blocked[destination_idx]
+                || tile == TileType::ClosedDoor
+                || tile.needs_swimming()
+                || tile.is_harmful()
+            {
+                continue;
+            }
+
+            let idx = map.xy_idx(pos.x, pos.y);
+            map.blocked[idx] = false;
+            pos.x = x;
+            pos.y = y;
+            map.blocked[destination_idx] = true;
+            entity_moved
+                .insert(entity, EntityMoved {})
+                .expect("Unable to insert marker");
+        }
+    }
+}
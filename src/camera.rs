@@ -0,0 +1,150 @@
+//! Camera that renders the part of the map around the player, so levels can be bigger than the screen.
+
+use crate::{Map, Position, Renderable, TileType, components::Hidden};
+use rltk::{Point, RGB, Rltk};
+use specs::prelude::*;
+
+/// Width of the map viewport on screen, in tiles.
+pub const VIEW_WIDTH: i32 = 80;
+/// Height of the map viewport on screen, in tiles, leaving room for the UI below it.
+pub const VIEW_HEIGHT: i32 = 43;
+
+/// Map coordinates of the top-left corner of the viewport.
+///
+/// The viewport is centered on the player, but stops at the map's edges, so a map no bigger than the screen never scrolls.
+pub fn view_origin(ecs: &World) -> Point {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let x = (player_pos.x - VIEW_WIDTH / 2).clamp(0, i32::max(0, map.width - VIEW_WIDTH));
+    let y = (player_pos.y - VIEW_HEIGHT / 2).clamp(0, i32::max(0, map.height - VIEW_HEIGHT));
+    Point::new(x, y)
+}
+
+/// Convert a screen position, such as the mouse's, to map coordinates.
+///
+/// Returns None if the position isn't over the map.
+pub fn screen_to_map(ecs: &World, screen_pos: (i32, i32)) -> Option<Point> {
+    let screen_pos = Point::new(screen_pos.0, screen_pos.1);
+    if !is_in_view(screen_pos) {
+        return None;
+    }
+
+    let origin = view_origin(ecs);
+    let map = ecs.fetch::<Map>();
+    let map_pos = Point::new(origin.x + screen_pos.x, origin.y + screen_pos.y);
+    if map_pos.x >= map.width || map_pos.y >= map.height {
+        return None;
+    }
+    Some(map_pos)
+}
+
+/// Convert map coordinates to a screen position.
+///
+/// Returns None if the position is outside the viewport.
+pub fn map_to_screen(ecs: &World, map_pos: Point) -> Option<Point> {
+    let origin = view_origin(ecs);
+    let screen_pos = Point::new(map_pos.x - origin.x, map_pos.y - origin.y);
+    if !is_in_view(screen_pos) {
+        return None;
+    }
+    Some(screen_pos)
+}
+
+/// Check if a screen position is inside the viewport.
+fn is_in_view(screen_pos: Point) -> bool {
+    (0..VIEW_WIDTH).contains(&screen_pos.x) && (0..VIEW_HEIGHT).contains(&screen_pos.y)
+}
+
+/// Render the revealed map inside the viewport, and every visible entity on it.
+pub fn render_camera(ecs: &World, ctx: &mut Rltk) {
+    let map = ecs.fetch::<Map>();
+    let origin = view_origin(ecs);
+
+    for screen_y in 0..VIEW_HEIGHT {
+        for screen_x in 0..VIEW_WIDTH {
+            let (x, y) = (origin.x + screen_x, origin.y + screen_y);
+            if x >= map.width || y >= map.height {
+                continue;
+            }
+
+            // Render tile, if revealed, depending on its type.
+            let idx = map.xy_idx(x, y);
+            if map.revealed_tiles[idx] {
+                let (glyph, mut fg) = tile_glyph(map.tiles[idx]);
+                // Remember, might be revealed, but not currently visible.
+                if !map.visible_tiles[idx] {
+                    fg = fg.to_greyscale()
+                }
+                ctx.set(screen_x, screen_y, fg, RGB::from_f32(0.0, 0.0, 0.0), glyph);
+            }
+        }
+    }
+
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let hidden = ecs.read_storage::<Hidden>();
+
+    // Render loop, skipping anything the player hasn't spotted.
+    let mut data = (&positions, &renderables, !&hidden)
+        .join()
+        .map(|(pos, render, _hidden)| (pos, render))
+        .collect::<Vec<_>>();
+    data.sort_by_key(|&a| std::cmp::Reverse(a.1.render_order));
+    for (pos, render) in data.iter() {
+        let idx = map.xy_idx(pos.x, pos.y);
+        if !map.visible_tiles[idx] {
+            continue;
+        }
+        if let Some(screen_pos) = map_to_screen(ecs, Point::new(pos.x, pos.y)) {
+            ctx.set(
+                screen_pos.x,
+                screen_pos.y,
+                render.fg,
+                render.bg,
+                render.glyph,
+            );
+        }
+    }
+}
+
+/// Render a snapshot of a level being built, all of it shown whether revealed or not.
+///
+/// Snapshots don't have a player to center on, so levels bigger than the screen show their middle.
+pub fn render_snapshot(map: &Map, ctx: &mut Rltk) {
+    let origin_x = i32::max(0, (map.width - VIEW_WIDTH) / 2);
+    let origin_y = i32::max(0, (map.height - VIEW_HEIGHT) / 2);
+
+    for screen_y in 0..VIEW_HEIGHT {
+        for screen_x in 0..VIEW_WIDTH {
+            let (x, y) = (origin_x + screen_x, origin_y + screen_y);
+            if x >= map.width || y >= map.height {
+                continue;
+            }
+
+            let (glyph, fg) = tile_glyph(map.tiles[map.xy_idx(x, y)]);
+            ctx.set(screen_x, screen_y, fg, RGB::from_f32(0.0, 0.0, 0.0), glyph);
+        }
+    }
+}
+
+/// Glyph and foreground color to draw a tile with.
+fn tile_glyph(tile: TileType) -> (rltk::FontCharType, RGB) {
+    match tile {
+        TileType::Floor => (rltk::to_cp437('.'), RGB::from_f32(0.0, 0.5, 0.5)),
+        TileType::Wall | TileType::SecretDoor => {
+            (rltk::to_cp437('#'), RGB::from_f32(0.0, 1.0, 0.0))
+        }
+        TileType::DownStairs => (rltk::to_cp437('>'), RGB::from_f32(0., 1.0, 1.0)),
+        TileType::UpStairs => (rltk::to_cp437('<'), RGB::from_f32(0., 1.0, 1.0)),
+        TileType::ClosedDoor => (rltk::to_cp437('+'), RGB::from_f32(0.6, 0.4, 0.2)),
+        TileType::OpenDoor => (rltk::to_cp437('\''), RGB::from_f32(0.6, 0.4, 0.2)),
+        TileType::ShallowWater => (rltk::to_cp437('~'), RGB::from_f32(0.3, 0.6, 1.0)),
+        TileType::DeepWater => (rltk::to_cp437('≈'), RGB::from_f32(0.0, 0.3, 1.0)),
+        TileType::Lava => (rltk::to_cp437('≈'), RGB::from_f32(1.0, 0.35, 0.0)),
+        TileType::Rubble => (rltk::to_cp437(';'), RGB::from_f32(0.6, 0.6, 0.5)),
+        TileType::Bridge => (rltk::to_cp437('='), RGB::from_f32(0.6, 0.4, 0.2)),
+        TileType::Chasm => (rltk::to_cp437('░'), RGB::from_f32(0.2, 0.2, 0.3)),
+        TileType::Road => (rltk::to_cp437('≡'), RGB::from_f32(0.6, 0.6, 0.6)),
+        TileType::Grass => (rltk::to_cp437('"'), RGB::from_f32(0.2, 0.7, 0.2)),
+    }
+}
@@ -10,10 +10,16 @@ use specs_derive::*;
 /// Marker flagging that an entity should be serialized and deserialized when the game is loaded or saved.
 pub struct SerializeMe;
 
-/// Special ECS component to help serialize game data on saves, specifically the game map.
+/// Special ECS component to help serialize game data on saves, specifically the game maps, run seed, gameplay generator, bosses met, and group ids handed out.
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct SerializationHelper {
     pub map: crate::map::Map,
+    pub dungeon: crate::dungeon::MasterDungeonMap,
+    pub seed: u64,
+    /// Seed the gameplay generator carries on from.
+    pub gameplay_seed: u64,
+    pub bosses: crate::bosses::SpawnedBosses,
+    pub group_ids: crate::spawner::GroupIds,
 }
 
 /// ECS component for entities that grant a melee power bonus.
@@ -152,6 +158,14 @@ pub struct Position {
     pub y: i32,
 }
 
+/// ECS component holding the position of an entity left behind on another level, in place of its Position.
+#[derive(Component, ConvertSaveload, Clone)]
+pub struct OtherLevelPosition {
+    pub x: i32,
+    pub y: i32,
+    pub depth: i32,
+}
+
 /// ECS component for things to be rendered in the UI.
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Renderable {
@@ -177,6 +191,97 @@ pub struct Viewshed {
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Monster {}
 
+/// ECS component flagging a peaceful NPC that wanders about, left alone by monster AI.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Bystander {}
+
+/// ECS component flagging a peaceful NPC that keeps shop, staying put and greeting the player.
+///
+/// Vendors don't buy or sell anything yet, as there's no currency or item value to trade with.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Vendor {}
+
+/// ECS component for a monster spawned as part of a group, like a war band, so group members can act together.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct GroupMember {
+    /// Name of the group in the raws.
+    pub group: String,
+    /// Shared by every member of the same group, and unique among the groups spawned this run.
+    pub id: usize,
+}
+
+/// ECS component for a unique named boss, marking its death with a message of its own.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Boss {
+    pub death_message: String,
+}
+
+/// ECS component for monsters that might drop something from a loot table in the raws when they die.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct LootTable {
+    /// Name of the loot table.
+    pub table: String,
+    /// Percent chance of a drop on the first level.
+    pub chance: i32,
+    /// Added to the chance for every level deeper.
+    pub chance_per_depth: i32,
+}
+
+impl LootTable {
+    /// Percent chance of a drop when dying at the given depth.
+    pub fn chance_at_depth(&self, depth: i32) -> i32 {
+        (self.chance + self.chance_per_depth * (depth - 1)).clamp(0, 100)
+    }
+}
+
+/// ECS component for entities that heal a number of hit points every turn.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Regeneration {
+    pub amount: i32,
+}
+
+/// ECS component for monsters that alert every monster on the level the first time they see the player.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct WarCry {}
+
+/// ECS component flagging an entity the player hasn't spotted yet, so it isn't drawn or named.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Hidden {}
+
+/// ECS component flagging an entity that fires when another entity steps onto its tile.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct EntryTrigger {}
+
+/// ECS component flagging an entity that moved this turn, so entry triggers can check its tile.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct EntityMoved {}
+
+/// ECS component flagging an entry trigger that is used up after firing once.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct SingleActivation {}
+
+/// ECS component for entry triggers that teleport whatever sets them off to a random spot on the level.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct TeleportsEntity {}
+
+/// ECS component for entry triggers that alert every monster on the level to the player.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RaisesAlarm {}
+
+/// ECS component for monsters that know where the player is for a number of turns, even out of sight.
+#[derive(Component, ConvertSaveload, Clone, Debug)]
+pub struct Alerted {
+    pub turns: i32,
+}
+
+/// ECS component flagging an entity that can swim across deep water.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Swimmer {}
+
+/// ECS component flagging an entity slowed by the terrain, which loses its next turn.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Slowed {}
+
 /// ECS component to hold the suffered damage for an entity.
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct SufferDamage {
@@ -197,3 +302,31 @@ impl SufferDamage {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loot(chance: i32, chance_per_depth: i32) -> LootTable {
+        LootTable {
+            table: "Goblin Loot".to_string(),
+            chance,
+            chance_per_depth,
+        }
+    }
+
+    #[test]
+    fn drop_chance_grows_with_depth() {
+        let loot = loot(20, 5);
+        assert_eq!(loot.chance_at_depth(1), 20);
+        assert_eq!(loot.chance_at_depth(2), 25);
+        assert_eq!(loot.chance_at_depth(5), 40);
+    }
+
+    #[test]
+    fn drop_chance_stays_a_percentage() {
+        assert_eq!(loot(90, 5).chance_at_depth(10), 100);
+        assert_eq!(loot(10, -5).chance_at_depth(10), 0);
+        assert_eq!(loot(150, 0).chance_at_depth(1), 100);
+    }
+}
@@ -1,7 +1,12 @@
 //! Logic for damage system.
 
-use crate::{CombatStats, Name, Player, RunState, SufferDamage, gamelog::GameLog};
-use rltk::console;
+use crate::{
+    CombatStats, Map, Name, Player, Position, RunState, SufferDamage,
+    components::{Boss, InBackpack, LootTable},
+    gamelog::GameLog,
+    raws,
+};
+use rltk::RandomNumberGenerator;
 use specs::prelude::*;
 
 /// System for applying damage in an ECS.
@@ -24,7 +29,7 @@ impl<'a> System<'a> for DamageSystem {
     }
 }
 
-/// To clean up dead entities on ticks, and log death messages.
+/// To clean up dead entities on ticks, log death messages, and drop whatever the dead were carrying.
 pub fn delete_the_dead(ecs: &mut World) {
     let mut dead: Vec<Entity> = Vec::new();
     // Scope below to keep borrow checker happy.
@@ -32,6 +37,7 @@ pub fn delete_the_dead(ecs: &mut World) {
         let combat_stats = ecs.read_storage::<CombatStats>();
         let players = ecs.read_storage::<Player>();
         let names = ecs.read_storage::<Name>();
+        let bosses = ecs.read_storage::<Boss>();
         let entities = ecs.entities();
         let mut log = ecs.write_resource::<GameLog>();
         for (entity, stats) in (&entities, &combat_stats).join() {
@@ -39,8 +45,9 @@ pub fn delete_the_dead(ecs: &mut World) {
                 let player = players.get(entity);
                 match player {
                     None => {
-                        let victim_name = names.get(entity);
-                        if let Some(victim_name) = victim_name {
+                        if let Some(boss) = bosses.get(entity) {
+                            log.entries.push(boss.death_message.clone());
+                        } else if let Some(victim_name) = names.get(entity) {
                             log.entries.push(format!("{} is dead", &victim_name.name));
                         }
                         dead.push(entity)
@@ -54,7 +61,76 @@ pub fn delete_the_dead(ecs: &mut World) {
         }
     }
 
+    drop_loot(ecs, &dead);
+    drop_carried_items(ecs, &dead);
+
     for victim in dead {
         ecs.delete_entity(victim).expect("Unable to delete");
     }
 }
+
+/// Roll each victim's loot table, spawning whatever it drops on the tile where it died.
+///
+/// The deeper the level, the better the chance of a drop.
+fn drop_loot(ecs: &mut World, victims: &[Entity]) {
+    let mut drops: Vec<(Entity, String, Position)> = Vec::new();
+    {
+        let depth = ecs.fetch::<Map>().depth;
+        let loot_tables = ecs.read_storage::<LootTable>();
+        let positions = ecs.read_storage::<Position>();
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        for victim in victims {
+            let (Some(loot), Some(pos)) = (loot_tables.get(*victim), positions.get(*victim)) else {
+                continue;
+            };
+            if rng.roll_dice(1, 100) > loot.chance_at_depth(depth) {
+                continue;
+            }
+            if let Some(item) = raws::raws().roll_loot(&loot.table, &mut rng) {
+                drops.push((*victim, item.to_string(), pos.clone()));
+            }
+        }
+    }
+
+    for (victim, item, pos) in drops {
+        if raws::spawn_named_entity(ecs, &item, pos.x, pos.y).is_none() {
+            continue;
+        }
+        if let Some(victim_name) = ecs.read_storage::<Name>().get(victim) {
+            ecs.write_resource::<GameLog>()
+                .entries
+                .push(format!("{} drops {}", victim_name.name, item));
+        }
+    }
+}
+
+/// Drop everything in the victims' backpacks onto the tiles where they died.
+fn drop_carried_items(ecs: &mut World, victims: &[Entity]) {
+    let entities = ecs.entities();
+    let names = ecs.read_storage::<Name>();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut backpack = ecs.write_storage::<InBackpack>();
+    let mut log = ecs.write_resource::<GameLog>();
+
+    let mut dropped: Vec<(Entity, Position)> = Vec::new();
+    for (item, carried) in (&entities, &backpack).join() {
+        if !victims.contains(&carried.owner) {
+            continue;
+        }
+        if let Some(pos) = positions.get(carried.owner) {
+            dropped.push((item, pos.clone()));
+            if let (Some(owner_name), Some(item_name)) = (names.get(carried.owner), names.get(item))
+            {
+                log.entries
+                    .push(format!("{} drops {}", owner_name.name, item_name.name));
+            }
+        }
+    }
+
+    for (item, pos) in dropped {
+        backpack.remove(item);
+        positions
+            .insert(item, pos)
+            .expect("Unable to insert position");
+    }
+}
@@ -0,0 +1,85 @@
+//! Master dungeon store, keeping levels and their entities around while the player is on another level.
+
+use crate::{Map, Player, Position, components::OtherLevelPosition};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::collections::HashMap;
+
+/// Resource holding every level the player has left, keyed by depth.
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct MasterDungeonMap {
+    maps: HashMap<i32, Map>,
+}
+
+impl MasterDungeonMap {
+    pub fn new() -> MasterDungeonMap {
+        MasterDungeonMap {
+            maps: HashMap::new(),
+        }
+    }
+
+    /// Store a copy of a level, replacing any previous copy at its depth.
+    pub fn store_map(&mut self, map: &Map) {
+        self.maps.insert(map.depth, map.clone());
+    }
+
+    /// Get a copy of the stored level at a depth, ready to be used as the current map.
+    pub fn get_map(&self, depth: i32) -> Option<Map> {
+        let mut map = self.maps.get(&depth)?.clone();
+        // tile_content isn't kept with stored levels, so give it fresh empty vectors.
+        map.tile_content = vec![Vec::new(); (map.width * map.height) as usize];
+        Some(map)
+    }
+}
+
+/// Freeze every entity on the current level, except the player, swapping its Position for an OtherLevelPosition.
+///
+/// Anything carried or equipped has no Position, so it stays with its owner.
+pub fn freeze_level_entities(ecs: &mut World) {
+    let entities = ecs.entities();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut other_level_positions = ecs.write_storage::<OtherLevelPosition>();
+    let players = ecs.read_storage::<Player>();
+    let depth = ecs.fetch::<Map>().depth;
+
+    let mut frozen: Vec<Entity> = Vec::new();
+    for (entity, pos, _player) in (&entities, &positions, !&players).join() {
+        other_level_positions
+            .insert(
+                entity,
+                OtherLevelPosition {
+                    x: pos.x,
+                    y: pos.y,
+                    depth,
+                },
+            )
+            .expect("Unable to insert position");
+        frozen.push(entity);
+    }
+
+    for entity in frozen {
+        positions.remove(entity);
+    }
+}
+
+/// Thaw every entity frozen on the current level, giving it back its Position.
+pub fn thaw_level_entities(ecs: &mut World) {
+    let entities = ecs.entities();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut other_level_positions = ecs.write_storage::<OtherLevelPosition>();
+    let depth = ecs.fetch::<Map>().depth;
+
+    let mut thawed: Vec<Entity> = Vec::new();
+    for (entity, pos) in (&entities, &other_level_positions).join() {
+        if pos.depth == depth {
+            positions
+                .insert(entity, Position { x: pos.x, y: pos.y })
+                .expect("Unable to insert position");
+            thawed.push(entity);
+        }
+    }
+
+    for entity in thawed {
+        other_level_positions.remove(entity);
+    }
+}
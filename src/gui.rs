@@ -1,9 +1,11 @@
 //! Logic for the GUI.
 
 use crate::{
-    CombatStats, Map, Name, Player, Position, RunState, State, Viewshed,
-    components::{Equipped, InBackpack},
+    CombatStats, Map, Name, Player, Position, RunState, State, Viewshed, camera,
+    components::{Equipped, Hidden, InBackpack},
     gamelog::GameLog,
+    map_builders,
+    run_seed::RunSeed,
 };
 use rltk::{Point, RGB, Rltk, VirtualKeyCode};
 use specs::prelude::*;
@@ -13,6 +15,7 @@ use specs::prelude::*;
 pub enum MainMenuSelection {
     NewGame,
     LoadGame,
+    Seed,
     Quit,
 }
 
@@ -43,7 +46,7 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
         .filter(|item| item.0.owner == *player_entity);
     let count = inventory.count();
 
-    let mut y = (25 - (count / 2)) as i32;
+    let y = (25 - (count / 2)) as i32;
     ctx.draw_box(
         15,
         y - 2,
@@ -68,11 +71,12 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
     );
 
     let mut equippable: Vec<Entity> = Vec::new();
-    let mut j = 0;
-    for (entity, _pack, name) in (&entities, &backpack, &names)
+    for (j, (entity, _pack, name)) in (&entities, &backpack, &names)
         .join()
         .filter(|item| item.1.owner == *player_entity)
+        .enumerate()
     {
+        let y = y + j as i32;
         ctx.set(
             17,
             y,
@@ -95,10 +99,8 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(21, y, &name.name);
         equippable.push(entity);
-        y += 1;
-        j += 1;
     }
 
     match ctx.key {
@@ -131,7 +133,7 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
         .filter(|item| item.0.owner == *player_entity);
     let count = inventory.count();
 
-    let mut y = (25 - (count / 2)) as i32;
+    let y = (25 - (count / 2)) as i32;
     ctx.draw_box(
         15,
         y - 2,
@@ -156,11 +158,12 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
     );
 
     let mut equippable: Vec<Entity> = Vec::new();
-    let mut j = 0;
-    for (entity, _pack, name) in (&entities, &bakcpack, &names)
+    for (j, (entity, _pack, name)) in (&entities, &bakcpack, &names)
         .join()
         .filter(|item| item.1.owner == *player_entity)
+        .enumerate()
     {
+        let y = y + j as i32;
         ctx.set(
             17,
             y,
@@ -183,10 +186,8 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(21, y, &name.name);
         equippable.push(entity);
-        y += 1;
-        j += 1;
     }
 
     match ctx.key {
@@ -219,7 +220,7 @@ pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Opti
         .filter(|item| item.0.owner == *player_entity);
     let count = inventory.count();
 
-    let mut y = (25 - (count / 2)) as i32;
+    let y = (25 - (count / 2)) as i32;
     ctx.draw_box(
         15,
         y - 2,
@@ -244,11 +245,12 @@ pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Opti
     );
 
     let mut equippable: Vec<Entity> = Vec::new();
-    let mut j = 0;
-    for (entity, _pack, name) in (&entities, &bakcpack, &names)
+    for (j, (entity, _pack, name)) in (&entities, &bakcpack, &names)
         .join()
         .filter(|item| item.1.owner == *player_entity)
+        .enumerate()
     {
+        let y = y + j as i32;
         ctx.set(
             17,
             y,
@@ -271,10 +273,8 @@ pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Opti
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(21, y, &name.name);
         equippable.push(entity);
-        y += 1;
-        j += 1;
     }
 
     match ctx.key {
@@ -332,7 +332,11 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
 
     // Display depth level.
     let map = ecs.fetch::<Map>();
-    let depth = format!("Depth: {}", map.depth);
+    let depth = if map.depth == map_builders::TOWN_DEPTH {
+        "Town".to_string()
+    } else {
+        format!("Depth: {}", map.depth)
+    };
     ctx.print_color(
         2,
         43,
@@ -343,12 +347,8 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
 
     // Display game log messages.
     let log = ecs.fetch::<GameLog>();
-    let mut y = 44;
-    for s in log.entries.iter().rev() {
-        if y < 49 {
-            ctx.print(2, y, s);
-        }
-        y += 1;
+    for (y, s) in (44..49).zip(log.entries.iter().rev()) {
+        ctx.print(2, y, s);
     }
 
     // Draw mouse cursor.
@@ -363,18 +363,20 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     let map = ecs.fetch::<Map>();
     let names = ecs.read_storage::<Name>();
     let positions = ecs.read_storage::<Position>();
+    let hidden = ecs.read_storage::<Hidden>();
 
     // Confirm mouse cursor is on the drawn map. Return if it isn't.
     let mouse_pos = ctx.mouse_pos();
-    if mouse_pos.0 >= map.width || mouse_pos.1 >= map.height {
+    let Some(mouse_map_pos) = camera::screen_to_map(ecs, mouse_pos) else {
         return;
-    }
+    };
 
     // Print tool tip to UI. If mouse position is on the left put the tip on the right, and vice versa.
     let mut tooltip: Vec<String> = Vec::new();
-    for (name, position) in (&names, &positions).join() {
+    for (name, position, _hidden) in (&names, &positions, !&hidden).join() {
         let idx = map.xy_idx(position.x, position.y);
-        if position.x == mouse_pos.0 && position.y == mouse_pos.1 && map.visible_tiles[idx] {
+        if position.x == mouse_map_pos.x && position.y == mouse_map_pos.y && map.visible_tiles[idx]
+        {
             tooltip.push(name.name.to_string());
         }
     }
@@ -391,8 +393,8 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
         if mouse_pos.0 > 40 {
             let arrow_pos = Point::new(mouse_pos.0 - 2, mouse_pos.1);
             let left_x = mouse_pos.0 - width;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
+            for (i, s) in tooltip.iter().enumerate() {
+                let y = mouse_pos.1 + i as i32;
                 ctx.print_color(
                     left_x,
                     y,
@@ -407,23 +409,22 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                         y,
                         RGB::named(rltk::WHITE),
                         RGB::named(rltk::GREY),
-                        &" ".to_string(),
+                        " ",
                     );
                 }
-                y += 1;
             }
             ctx.print_color(
                 arrow_pos.x,
                 arrow_pos.y,
                 RGB::named(rltk::WHITE),
                 RGB::named(rltk::GREY),
-                &"->".to_string(),
+                "->",
             );
         } else {
             let arrow_pos = Point::new(mouse_pos.0 + 1, mouse_pos.1);
             let left_x = mouse_pos.0 + 3;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
+            for (i, s) in tooltip.iter().enumerate() {
+                let y = mouse_pos.1 + i as i32;
                 ctx.print_color(
                     left_x + 1,
                     y,
@@ -438,17 +439,16 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                         y,
                         RGB::named(rltk::WHITE),
                         RGB::named(rltk::GREY),
-                        &" ".to_string(),
+                        " ",
                     );
                 }
-                y += 1;
             }
             ctx.print_color(
                 arrow_pos.x,
                 arrow_pos.y,
                 RGB::named(rltk::WHITE),
                 RGB::named(rltk::GREY),
-                &"<-".to_string(),
+                "<-",
             );
         }
     }
@@ -480,7 +480,9 @@ pub fn ranged_target(
         for idx in visible.visible_tiles.iter() {
             let distance = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *idx);
             if distance <= range as f32 {
-                ctx.set_bg(idx.x, idx.y, RGB::named(rltk::BLUE));
+                if let Some(screen_pos) = camera::map_to_screen(&gs.ecs, *idx) {
+                    ctx.set_bg(screen_pos.x, screen_pos.y, RGB::named(rltk::BLUE));
+                }
                 available_cells.push(idx);
             }
         }
@@ -490,19 +492,12 @@ pub fn ranged_target(
 
     // Draw mouse cursor.
     let mouse_pos = ctx.mouse_pos();
-    let mut valid_target = false;
-    for idx in available_cells.iter() {
-        if idx.x == mouse_pos.0 && idx.y == mouse_pos.1 {
-            valid_target = true;
-        }
-    }
-    if valid_target {
+    let valid_target = camera::screen_to_map(&gs.ecs, mouse_pos)
+        .filter(|target| available_cells.contains(&target));
+    if let Some(target) = valid_target {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::CYAN));
         if ctx.left_click {
-            return (
-                ItemMenuResult::Selected,
-                Some(Point::new(mouse_pos.0, mouse_pos.1)),
-            );
+            return (ItemMenuResult::Selected, Some(target));
         }
     } else {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::RED));
@@ -567,15 +562,32 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
             }
         }
 
-        if selection == MainMenuSelection::Quit {
+        let seed_text = format!("Seed: {}", gs.ecs.fetch::<RunSeed>().seed);
+        if selection == MainMenuSelection::Seed {
             ctx.print_color_centered(
                 26,
                 RGB::named(rltk::MAGENTA),
                 RGB::named(rltk::BLACK),
+                &seed_text,
+            );
+        } else {
+            ctx.print_color_centered(
+                26,
+                RGB::named(rltk::WHITE),
+                RGB::named(rltk::BLACK),
+                &seed_text,
+            );
+        }
+
+        if selection == MainMenuSelection::Quit {
+            ctx.print_color_centered(
+                27,
+                RGB::named(rltk::MAGENTA),
+                RGB::named(rltk::BLACK),
                 "Quit",
             );
         } else {
-            ctx.print_color_centered(26, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), "Quit");
+            ctx.print_color_centered(27, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), "Quit");
         }
 
         match ctx.key {
@@ -595,7 +607,8 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
                     match selection {
                         MainMenuSelection::NewGame => newselection = MainMenuSelection::Quit,
                         MainMenuSelection::LoadGame => newselection = MainMenuSelection::NewGame,
-                        MainMenuSelection::Quit => newselection = MainMenuSelection::LoadGame,
+                        MainMenuSelection::Seed => newselection = MainMenuSelection::LoadGame,
+                        MainMenuSelection::Quit => newselection = MainMenuSelection::Seed,
                     }
                     if newselection == MainMenuSelection::LoadGame && !save_exists {
                         newselection = MainMenuSelection::NewGame;
@@ -608,11 +621,12 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
                     let mut newselection;
                     match selection {
                         MainMenuSelection::NewGame => newselection = MainMenuSelection::LoadGame,
-                        MainMenuSelection::LoadGame => newselection = MainMenuSelection::Quit,
+                        MainMenuSelection::LoadGame => newselection = MainMenuSelection::Seed,
+                        MainMenuSelection::Seed => newselection = MainMenuSelection::Quit,
                         MainMenuSelection::Quit => newselection = MainMenuSelection::NewGame,
                     }
                     if newselection == MainMenuSelection::LoadGame && !save_exists {
-                        newselection = MainMenuSelection::Quit;
+                        newselection = MainMenuSelection::Seed;
                     }
                     return MainMenuResult::NoSelection {
                         selected: newselection,
@@ -636,6 +650,79 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
     }
 }
 
+/// Shows the seed entry box over the main menu, letting the player type in the seed for the next run.
+pub fn enter_seed(gs: &mut State, ctx: &mut Rltk) -> ItemMenuResult {
+    let mut run_seed = gs.ecs.write_resource::<RunSeed>();
+
+    ctx.print_color_centered(
+        15,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "absolutechaos",
+    );
+    ctx.draw_box(
+        25,
+        23,
+        30,
+        4,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color_centered(
+        24,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Enter a seed",
+    );
+    ctx.print_color_centered(
+        25,
+        RGB::named(rltk::MAGENTA),
+        RGB::named(rltk::BLACK),
+        format!("{}_", run_seed.seed),
+    );
+    ctx.print_color_centered(
+        26,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "ENTER to accept, ESCAPE to cancel",
+    );
+
+    let digit = match ctx.key {
+        Some(VirtualKeyCode::Key0) | Some(VirtualKeyCode::Numpad0) => Some(0),
+        Some(VirtualKeyCode::Key1) | Some(VirtualKeyCode::Numpad1) => Some(1),
+        Some(VirtualKeyCode::Key2) | Some(VirtualKeyCode::Numpad2) => Some(2),
+        Some(VirtualKeyCode::Key3) | Some(VirtualKeyCode::Numpad3) => Some(3),
+        Some(VirtualKeyCode::Key4) | Some(VirtualKeyCode::Numpad4) => Some(4),
+        Some(VirtualKeyCode::Key5) | Some(VirtualKeyCode::Numpad5) => Some(5),
+        Some(VirtualKeyCode::Key6) | Some(VirtualKeyCode::Numpad6) => Some(6),
+        Some(VirtualKeyCode::Key7) | Some(VirtualKeyCode::Numpad7) => Some(7),
+        Some(VirtualKeyCode::Key8) | Some(VirtualKeyCode::Numpad8) => Some(8),
+        Some(VirtualKeyCode::Key9) | Some(VirtualKeyCode::Numpad9) => Some(9),
+        _ => None,
+    };
+    if let Some(digit) = digit {
+        // Ignore digits that would overflow the seed.
+        if let Some(seed) = run_seed
+            .seed
+            .checked_mul(10)
+            .and_then(|seed| seed.checked_add(digit))
+        {
+            run_seed.seed = seed;
+        }
+        return ItemMenuResult::NoResponse;
+    }
+
+    match ctx.key {
+        Some(VirtualKeyCode::Back) => {
+            run_seed.seed /= 10;
+            ItemMenuResult::NoResponse
+        }
+        Some(VirtualKeyCode::Return) => ItemMenuResult::Selected,
+        Some(VirtualKeyCode::Escape) => ItemMenuResult::Cancel,
+        _ => ItemMenuResult::NoResponse,
+    }
+}
+
 /// User selection from the game over screen.
 #[derive(PartialEq, Copy, Clone)]
 pub enum GameOverResult {
@@ -643,8 +730,8 @@ pub enum GameOverResult {
     QuitToMenu,
 }
 
-/// Render game over screen.
-pub fn game_over(ctx: &mut Rltk) -> GameOverResult {
+/// Render game over screen, including the run seed so the run can be replayed.
+pub fn game_over(ecs: &World, ctx: &mut Rltk) -> GameOverResult {
     ctx.print_color_centered(
         15,
         RGB::named(rltk::YELLOW),
@@ -663,9 +750,15 @@ pub fn game_over(ctx: &mut Rltk) -> GameOverResult {
         RGB::named(rltk::BLACK),
         "That day, sadly, is not in this chaper...",
     );
-
     ctx.print_color_centered(
         20,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+        format!("Run seed: {}", ecs.fetch::<RunSeed>().seed),
+    );
+
+    ctx.print_color_centered(
+        22,
         RGB::named(rltk::MAGENTA),
         RGB::named(rltk::BLACK),
         "Press any key to return to the menu.",
@@ -0,0 +1,72 @@
+//! Level themes, giving each depth its own feel through its layout, terrain, spawns, and an arrival message.
+
+/// Theme a level is generated around.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum LevelTheme {
+    /// Plain dungeon, with a bit of everything.
+    Dungeon,
+    /// Cramped tunnels crawling with goblins and their traps.
+    GoblinWarren,
+    /// Old tombs half sunk under water, with scrolls left among the dead.
+    FloodedCrypt,
+    /// Orderly halls garrisoned by well-armed orcs.
+    OrcBarracks,
+    /// Scorched caves split by lava and chasms.
+    MoltenDepths,
+}
+
+/// Themes below the first level, repeating in this order as the player goes deeper.
+///
+/// Plain dungeon lands on every fifth depth, where the labyrinths are.
+const THEME_CYCLE: [LevelTheme; 5] = [
+    LevelTheme::GoblinWarren,
+    LevelTheme::FloodedCrypt,
+    LevelTheme::OrcBarracks,
+    LevelTheme::Dungeon,
+    LevelTheme::MoltenDepths,
+];
+
+impl LevelTheme {
+    /// Theme of the level at the given depth.
+    pub fn for_depth(depth: i32) -> LevelTheme {
+        if depth < 2 {
+            return LevelTheme::Dungeon;
+        }
+        THEME_CYCLE[(depth - 2) as usize % THEME_CYCLE.len()]
+    }
+
+    /// Message for the game log when the player first arrives on a level with this theme.
+    pub fn arrival_message(self) -> &'static str {
+        match self {
+            LevelTheme::Dungeon => "The dungeon stretches on into the dark.",
+            LevelTheme::GoblinWarren => "The stench of goblin fills these cramped tunnels.",
+            LevelTheme::FloodedCrypt => "Water drips through the tombs of a flooded crypt.",
+            LevelTheme::OrcBarracks => "You hear the clank of armor. Orcs are garrisoned here.",
+            LevelTheme::MoltenDepths => "A wave of heat rolls up from the molten depths.",
+        }
+    }
+
+    /// Scale the spawn weight of the named monster, item, or trap to suit the theme.
+    pub fn spawn_weight(self, name: &str, weight: i32) -> i32 {
+        let percent = match (self, name) {
+            (LevelTheme::GoblinWarren, "Goblin" | "Goblin War Band") => 300,
+            (LevelTheme::GoblinWarren, "Orc" | "Orc Patrol") => 50,
+            (LevelTheme::GoblinWarren, "Bear Trap" | "Pit Trap") => 200,
+            (LevelTheme::FloodedCrypt, "Fireball Scroll") => 50,
+            (
+                LevelTheme::FloodedCrypt,
+                "Confusion Scroll" | "Magic Missile Scroll" | "Teleport Trap",
+            ) => 200,
+            (LevelTheme::OrcBarracks, "Orc" | "Orc Patrol") => 300,
+            (LevelTheme::OrcBarracks, "Goblin" | "Goblin War Band") => 50,
+            (
+                LevelTheme::OrcBarracks,
+                "Dagger" | "Shield" | "Longsword" | "Tower Shield" | "Alarm Trap",
+            ) => 200,
+            (LevelTheme::MoltenDepths, "Fireball Scroll") => 300,
+            (LevelTheme::MoltenDepths, "Bear Trap" | "Pit Trap") => 50,
+            _ => 100,
+        };
+        weight * percent / 100
+    }
+}
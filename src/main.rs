@@ -1,25 +1,40 @@
+mod bosses;
+mod bystander_ai_system;
+mod camera;
 mod components;
 mod damage_system;
+mod dungeon;
 mod gamelog;
 mod gui;
 mod inventory_system;
+mod level_theme;
 mod map;
+mod map_builders;
 mod map_indexing_system;
+mod mapgen;
 mod melee_combat_system;
 mod monster_ai_system;
 mod player;
 mod random_table;
+mod raws;
 mod rect;
+mod regeneration_system;
+mod run_seed;
 mod saveload_system;
 mod spawner;
+mod terrain_system;
+mod trigger_system;
 mod visibility_system;
 
 use components::{
-    AreaOfEffect, Confusion, Consumable, DefenseBonus, Equipable, Equipped, InBackpack,
-    InflictsDamage, Item, MeleePowerBonus, ProvidesHealing, Ranged, SerializationHelper,
-    SerializeMe, WantsToDropItem, WantsToPickupItem, WantsToRemoveItem, WantsToUseItem,
+    Alerted, AreaOfEffect, Boss, Bystander, Confusion, Consumable, DefenseBonus, EntityMoved,
+    EntryTrigger, Equipable, Equipped, GroupMember, Hidden, InBackpack, InflictsDamage, Item,
+    LootTable, MeleePowerBonus, OtherLevelPosition, ProvidesHealing, RaisesAlarm, Ranged,
+    Regeneration, SerializationHelper, SerializeMe, SingleActivation, Slowed, Swimmer,
+    TeleportsEntity, Vendor, WantsToDropItem, WantsToPickupItem, WantsToRemoveItem, WantsToUseItem,
+    WarCry,
 };
-use rltk::{GameState, Point, Rltk};
+use rltk::{GameState, Point, RGB, Rltk};
 use specs::{
     prelude::*,
     saveload::{SimpleMarker, SimpleMarkerAllocator},
@@ -29,21 +44,39 @@ pub use components::{
     BlocksTile, CombatStats, Monster, Name, Player, Position, Renderable, SufferDamage, Viewshed,
     WantsToMelee,
 };
-pub use map::{Map, TileType, draw_map, new_map_rooms_and_corridors};
+pub use map::{Map, TileType};
 pub use player::player_input;
 
+use bosses::SpawnedBosses;
+use bystander_ai_system::BystanderAI;
 use damage_system::DamageSystem;
+use dungeon::MasterDungeonMap;
 use inventory_system::{ItemCollectionSystem, ItemDropSystem, ItemRemoveSystem, ItemUseSystem};
+use level_theme::LevelTheme;
 use map_indexing_system::MapIndexingSystem;
 use melee_combat_system::MeleeCombatSystem;
 use monster_ai_system::MonsterAI;
+use regeneration_system::RegenerationSystem;
+use run_seed::RunSeed;
+use spawner::GroupIds;
+use terrain_system::TerrainSystem;
+use trigger_system::TriggerSystem;
 use visibility_system::VisibilitySystem;
 
 /// Game state.
 pub struct State {
     pub ecs: World,
+    /// Snapshots taken while building the current level, replayed before it starts when recording is on.
+    mapgen_history: Vec<Map>,
+    /// Snapshot being shown while replaying.
+    mapgen_index: usize,
+    /// Milliseconds the current snapshot has been shown for.
+    mapgen_timer: f32,
 }
 
+/// Milliseconds each snapshot is shown for when replaying level generation.
+const MAPGEN_FRAME_TIME: f32 = 100.0;
+
 impl GameState for State {
     /// Run basic turn-based tick loop.
     fn tick(&mut self, ctx: &mut Rltk) {
@@ -56,31 +89,37 @@ impl GameState for State {
         ctx.cls();
 
         match newrunstate {
-            RunState::MainMenu { .. } => {}
+            RunState::MainMenu { .. } | RunState::EnterSeed { .. } | RunState::MapGeneration => {}
             _ => {
-                draw_map(&self.ecs, ctx);
-
-                // Scope here to scope passing mutable self.ecs, fixing error.
-                {
-                    let positions = self.ecs.read_storage::<Position>();
-                    let renderables = self.ecs.read_storage::<Renderable>();
-                    let map = self.ecs.fetch::<Map>();
-
-                    // Render loop.
-                    let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
-                    data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
-                    for (pos, render) in data.iter() {
-                        let idx = map.xy_idx(pos.x, pos.y);
-                        if map.visible_tiles[idx] {
-                            ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph)
-                        }
-                    }
-                    gui::draw_ui(&self.ecs, ctx);
-                }
+                camera::render_camera(&self.ecs, ctx);
+                gui::draw_ui(&self.ecs, ctx);
             }
         }
 
         match newrunstate {
+            RunState::MapGeneration => {
+                camera::render_snapshot(&self.mapgen_history[self.mapgen_index], ctx);
+                ctx.print_color(
+                    2,
+                    43,
+                    RGB::named(rltk::YELLOW),
+                    RGB::named(rltk::BLACK),
+                    format!(
+                        "Building level: step {} of {}. Press any key to skip.",
+                        self.mapgen_index + 1,
+                        self.mapgen_history.len()
+                    ),
+                );
+
+                self.mapgen_timer += ctx.frame_time_ms;
+                if self.mapgen_timer > MAPGEN_FRAME_TIME {
+                    self.mapgen_timer = 0.0;
+                    self.mapgen_index += 1;
+                }
+                if self.mapgen_index >= self.mapgen_history.len() || ctx.key.is_some() {
+                    newrunstate = RunState::PreRun;
+                }
+            }
             RunState::PreRun => {
                 self.run_systems();
                 self.ecs.maintain();
@@ -174,17 +213,54 @@ impl GameState for State {
                         }
                     }
                     gui::MainMenuResult::Selected { selected } => match selected {
-                        gui::MainMenuSelection::NewGame => newrunstate = RunState::PreRun,
+                        gui::MainMenuSelection::NewGame => {
+                            self.new_run();
+                            newrunstate = self.level_start_state();
+                        }
                         gui::MainMenuSelection::LoadGame => {
-                            saveload_system::load_game(&mut self.ecs);
-                            newrunstate = RunState::AwaitInput;
-                            // Delete save because rougelikes have permadeath.
-                            saveload_system::delete_save();
+                            match saveload_system::load_game(&mut self.ecs) {
+                                Ok(()) => {
+                                    newrunstate = RunState::AwaitInput;
+                                    // Delete save because rougelikes have permadeath.
+                                    saveload_system::delete_save();
+                                }
+                                Err(reason) => {
+                                    rltk::console::log(reason);
+                                    newrunstate = RunState::MainMenu {
+                                        menu_selection: gui::MainMenuSelection::NewGame,
+                                    };
+                                }
+                            }
+                        }
+                        gui::MainMenuSelection::Seed => {
+                            // Typing starts from a blank seed; the old one comes back on cancel.
+                            let mut run_seed = self.ecs.write_resource::<RunSeed>();
+                            newrunstate = RunState::EnterSeed {
+                                previous_seed: run_seed.seed,
+                            };
+                            run_seed.seed = 0;
                         }
                         gui::MainMenuSelection::Quit => ::std::process::exit(0),
                     },
                 }
             }
+            RunState::EnterSeed { previous_seed } => {
+                let result = gui::enter_seed(self, ctx);
+                match result {
+                    gui::ItemMenuResult::Cancel => {
+                        self.ecs.write_resource::<RunSeed>().seed = previous_seed;
+                        newrunstate = RunState::MainMenu {
+                            menu_selection: gui::MainMenuSelection::Seed,
+                        };
+                    }
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => {
+                        newrunstate = RunState::MainMenu {
+                            menu_selection: gui::MainMenuSelection::NewGame,
+                        };
+                    }
+                }
+            }
             RunState::SaveGame => {
                 saveload_system::save_game(&mut self.ecs);
                 newrunstate = RunState::MainMenu {
@@ -192,8 +268,12 @@ impl GameState for State {
                 }
             }
             RunState::NextLevel => {
-                self.goto_next_level();
-                newrunstate = RunState::PreRun;
+                self.goto_level(1);
+                newrunstate = self.level_start_state();
+            }
+            RunState::PreviousLevel => {
+                self.goto_level(-1);
+                newrunstate = self.level_start_state();
             }
             RunState::ShowRemoveItem => {
                 let result = gui::remove_item_menu(self, ctx);
@@ -214,11 +294,12 @@ impl GameState for State {
                 }
             }
             RunState::GameOver => {
-                let result = gui::game_over(ctx);
+                let result = gui::game_over(&self.ecs, ctx);
                 match result {
                     gui::GameOverResult::NoSelection => {}
                     gui::GameOverResult::QuitToMenu => {
-                        self.game_over_cleanup();
+                        // The next run gets a fresh seed unless the player enters one.
+                        *self.ecs.write_resource::<RunSeed>() = RunSeed::random();
                         newrunstate = RunState::MainMenu {
                             menu_selection: gui::MainMenuSelection::NewGame,
                         };
@@ -251,8 +332,14 @@ pub enum RunState {
     MainMenu {
         menu_selection: gui::MainMenuSelection,
     },
+    EnterSeed {
+        previous_seed: u64,
+    },
     SaveGame,
+    /// Replaying the snapshots taken while the level was built.
+    MapGeneration,
     NextLevel,
+    PreviousLevel,
     ShowRemoveItem,
     GameOver,
 }
@@ -266,9 +353,21 @@ impl State {
         let mut mob = MonsterAI {};
         mob.run_now(&self.ecs);
 
+        let mut bystanders = BystanderAI {};
+        bystanders.run_now(&self.ecs);
+
+        let mut regeneration = RegenerationSystem {};
+        regeneration.run_now(&self.ecs);
+
         let mut mapindex = MapIndexingSystem {};
         mapindex.run_now(&self.ecs);
 
+        let mut terrain = TerrainSystem {};
+        terrain.run_now(&self.ecs);
+
+        let mut triggers = TriggerSystem {};
+        triggers.run_now(&self.ecs);
+
         let mut melee = MeleeCombatSystem {};
         melee.run_now(&self.ecs);
 
@@ -290,106 +389,91 @@ impl State {
         self.ecs.maintain();
     }
 
-    /// Fetches all entities that are swapped out when the level changes. Basically, anything that isn't the player or their equipment.
-    fn entities_to_remove_on_level_change(&mut self) -> Vec<Entity> {
-        let entities = self.ecs.entities();
-        let player = self.ecs.read_storage::<Player>();
-        let backpack = self.ecs.read_storage::<InBackpack>();
-        let player_entity = self.ecs.fetch::<Entity>();
-        let equipped = self.ecs.read_storage::<Equipped>();
-
-        let mut to_delete: Vec<Entity> = Vec::new();
-        for entity in entities.join() {
-            let mut should_delete = true;
+    /// Change level by offset, storing the current level and restoring the destination if it was visited before.
+    fn goto_level(&mut self, offset: i32) {
+        // Only a newly built level has a generation to replay.
+        self.mapgen_history.clear();
 
-            // Don't delete the player.
-            let p = player.get(entity);
-            if let Some(_p) = p {
-                should_delete = false;
-            }
-
-            // Don't delete the player's equipment.
-            // Check this by seeing if it's in the player's backpack.
-            let bp = backpack.get(entity);
-            if let Some(bp) = bp {
-                if bp.owner == *player_entity {
-                    should_delete = false;
-                }
-            }
-
-            // Don't delete player-equipped entities.
-            let eq = equipped.get(entity);
-            if let Some(eq) = eq {
-                if eq.owner == *player_entity {
-                    should_delete = false;
-                }
-            }
-
-            if should_delete {
-                to_delete.push(entity);
-            }
-        }
-
-        to_delete
-    }
-
-    fn goto_next_level(&mut self) {
-        // Delete entities that aren't the player or their equipment.
-        let to_delete = self.entities_to_remove_on_level_change();
-        for target in to_delete {
-            self.ecs
-                .delete_entity(target)
-                .expect("Unable to delete entity");
-        }
-
-        // Build a new map and place the player on the map.
-        let worldmap;
-        let current_depth: i32;
-        // New scope to keep the borrow-checker happy with the mut ecs.
+        // Keep the level being left, and everything on it, for when the player comes back.
+        dungeon::freeze_level_entities(&mut self.ecs);
+        let current_depth;
         {
-            let mut worldmap_resource = self.ecs.write_resource::<Map>();
+            let worldmap_resource = self.ecs.fetch::<Map>();
+            self.ecs
+                .write_resource::<MasterDungeonMap>()
+                .store_map(&worldmap_resource);
             current_depth = worldmap_resource.depth;
-            *worldmap_resource = new_map_rooms_and_corridors(current_depth + 1);
-            worldmap = worldmap_resource.clone();
         }
-
-        // Spawn baddies.
-        for room in worldmap.rooms.iter().skip(1) {
-            spawner::spawn_room(&mut self.ecs, room, current_depth + 1);
+        let new_depth = current_depth + offset;
+
+        let stored_map = self.ecs.fetch::<MasterDungeonMap>().get_map(new_depth);
+        if let Some(map) = stored_map {
+            // Arrive on the stairs that lead back where the player came from.
+            let arrival_tile = if offset < 0 {
+                TileType::DownStairs
+            } else {
+                TileType::UpStairs
+            };
+            let arrival_idx = map
+                .tiles
+                .iter()
+                .position(|tile| *tile == arrival_tile)
+                .unwrap_or_else(|| {
+                    rltk::console::log(format!(
+                        "No stairs to arrive on at level {}, so arriving at its start",
+                        new_depth
+                    ));
+                    map.start_idx
+                });
+            let (x, y) = (
+                arrival_idx as i32 % map.width,
+                arrival_idx as i32 / map.width,
+            );
+            *self.ecs.write_resource::<Map>() = map;
+            dungeon::thaw_level_entities(&mut self.ecs);
+            self.place_player(x, y);
+
+            let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
+            if offset < 0 {
+                gamelog
+                    .entries
+                    .push("You climb back up to the previous level.".to_string());
+            } else {
+                gamelog
+                    .entries
+                    .push("You descend back down to the next level.".to_string());
+            }
+            return;
         }
 
-        // Place the player and update resources.
-        let (player_x, player_y) = worldmap.rooms[0].center();
-        let mut player_position = self.ecs.write_resource::<Point>();
-        *player_position = Point::new(player_x, player_y);
-        let mut position_components = self.ecs.write_storage::<Position>();
-        let player_entity = self.ecs.fetch::<Entity>();
-        let player_pos_comp = position_components.get_mut(*player_entity);
-        if let Some(player_pos_comp) = player_pos_comp {
-            player_pos_comp.x = player_x;
-            player_pos_comp.y = player_y;
-        }
+        // Build a new map and place the player on the map.
+        self.generate_world_map(new_depth);
 
-        // Mark the player's visibility as dirty.
-        let mut viewshed_components = self.ecs.write_storage::<Viewshed>();
-        let vs = viewshed_components.get_mut(*player_entity);
-        if let Some(vs) = vs {
-            vs.dirty = true;
+        // Notify the player, set the scene, and give them a little health.
+        {
+            let player_entity = self.ecs.fetch::<Entity>();
+            let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
+            gamelog
+                .entries
+                .push("You descend to the next level and take a moment to heal.".to_string());
+            gamelog.entries.push(
+                LevelTheme::for_depth(new_depth)
+                    .arrival_message()
+                    .to_string(),
+            );
+            let mut player_health_store = self.ecs.write_storage::<CombatStats>();
+            let player_health = player_health_store.get_mut(*player_entity);
+            if let Some(player_health) = player_health {
+                player_health.hp = i32::max(player_health.hp, player_health.max_hp / 2);
+            }
         }
 
-        // Notify the player and give them a little health.
-        let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
-        gamelog
-            .entries
-            .push("You descend to the next level and take a moment to heal.".to_string());
-        let mut player_health_store = self.ecs.write_storage::<CombatStats>();
-        let player_health = player_health_store.get_mut(*player_entity);
-        if let Some(player_health) = player_health {
-            player_health.hp = i32::max(player_health.hp, player_health.max_hp / 2);
-        }
+        // Bosses waiting on the level announce themselves once the scene is set.
+        bosses::spawn_bosses(&mut self.ecs, new_depth);
     }
 
-    fn game_over_cleanup(&mut self) {
+    /// Start a new run from the current run seed.
+    fn new_run(&mut self) {
         // Delete everything.
         let mut to_delete = Vec::new();
         for e in self.ecs.entities().join() {
@@ -399,36 +483,86 @@ impl State {
             self.ecs.delete_entity(*del).expect("Deletion failed");
         }
 
-        // Build a new map.
-        let worldmap;
+        // Spawn a new player, then build a new map around them.
+        {
+            let player_entity = spawner::player(&mut self.ecs, 0, 0);
+            let mut player_entity_writer = self.ecs.write_resource::<Entity>();
+            *player_entity_writer = player_entity;
+        }
+
+        // Restart gameplay rolls from the seed so the whole run can be replayed.
+        let rng = self.ecs.fetch::<RunSeed>().gameplay_rng();
+        self.ecs.insert(rng);
+        self.ecs.insert(MasterDungeonMap::new());
+        self.ecs.insert(SpawnedBosses::new());
+        self.ecs.insert(GroupIds::new());
+
+        self.generate_world_map(map_builders::TOWN_DEPTH);
+    }
+
+    /// Build a map for the given depth, spawn its entities, and place the player at its starting position.
+    fn generate_world_map(&mut self, new_depth: i32) {
+        let mut rng = self.ecs.fetch::<RunSeed>().level_rng(new_depth);
+        let mut builder = map_builders::build_level(new_depth, &mut rng);
+        self.mapgen_history = builder.get_snapshot_history().clone();
+        self.mapgen_index = 0;
+        self.mapgen_timer = 0.0;
+        let mut player_start = builder.get_starting_position();
         {
             let mut worldmap_resource = self.ecs.write_resource::<Map>();
-            *worldmap_resource = new_map_rooms_and_corridors(1);
-            worldmap = worldmap_resource.clone();
+            *worldmap_resource = builder.get_map();
+
+            // Every level below town has stairs back up where the player arrives.
+            // The start is moved onto the nearest floor first, so the stairs never replace the stairs down or a door.
+            if new_depth > map_builders::TOWN_DEPTH {
+                let start_idx = worldmap_resource.xy_idx(player_start.x, player_start.y);
+                match map_builders::up_stairs_idx(&worldmap_resource, start_idx) {
+                    Some(stairs_idx) => {
+                        worldmap_resource.tiles[stairs_idx] = TileType::UpStairs;
+                        player_start = Position {
+                            x: stairs_idx as i32 % worldmap_resource.width,
+                            y: stairs_idx as i32 / worldmap_resource.width,
+                        };
+                    }
+                    None => rltk::console::log(format!(
+                        "No floor near the start of level {} for stairs up",
+                        new_depth
+                    )),
+                }
+            }
+            worldmap_resource.start_idx = worldmap_resource.xy_idx(player_start.x, player_start.y);
         }
 
         // Spawn baddies.
-        for room in worldmap.rooms.iter().skip(1) {
-            spawner::spawn_room(&mut self.ecs, room, 1);
+        builder.spawn_entities(&mut self.ecs, &mut rng);
+
+        self.place_player(player_start.x, player_start.y);
+    }
+
+    /// State to start a level in once it's ready: replaying how it was built if there are snapshots, or straight into play.
+    fn level_start_state(&self) -> RunState {
+        if self.mapgen_history.is_empty() {
+            RunState::PreRun
+        } else {
+            RunState::MapGeneration
         }
+    }
 
-        // Place the player and update resources.
-        let (player_x, player_y) = worldmap.rooms[0].center();
-        let player_entity = spawner::player(&mut self.ecs, player_x, player_y);
+    /// Move the player to x, y on the current map and update resources.
+    fn place_player(&mut self, x: i32, y: i32) {
         let mut player_position = self.ecs.write_resource::<Point>();
-        *player_position = Point::new(player_x, player_y);
+        *player_position = Point::new(x, y);
         let mut position_components = self.ecs.write_storage::<Position>();
-        let mut player_entity_writer = self.ecs.write_resource::<Entity>();
-        *player_entity_writer = player_entity;
-        let player_pos_comp = position_components.get_mut(player_entity);
+        let player_entity = self.ecs.fetch::<Entity>();
+        let player_pos_comp = position_components.get_mut(*player_entity);
         if let Some(player_pos_comp) = player_pos_comp {
-            player_pos_comp.x = player_x;
-            player_pos_comp.y = player_y;
+            player_pos_comp.x = x;
+            player_pos_comp.y = y;
         }
 
         // Mark the player's visibility as dirty.
         let mut viewshed_components = self.ecs.write_storage::<Viewshed>();
-        let vs = viewshed_components.get_mut(player_entity);
+        let vs = viewshed_components.get_mut(*player_entity);
         if let Some(vs) = vs {
             vs.dirty = true;
         }
@@ -436,13 +570,30 @@ impl State {
 }
 
 fn main() -> rltk::BError {
+    // Read the raws up front, so a broken raws file is reported before anything else happens.
+    raws::raws();
+
+    // Build levels without opening a window, for tuning the generators.
+    if std::env::args().nth(1).as_deref() == Some("mapgen") {
+        mapgen::run();
+        return Ok(());
+    }
+
     use rltk::RltkBuilder;
     let mut context = RltkBuilder::simple80x50()
         .with_title("absolutechaos")
         .build()?;
     context.with_post_scanlines(true);
 
-    let mut gs = State { ecs: World::new() };
+    let mut gs = State {
+        ecs: World::new(),
+        mapgen_history: Vec::new(),
+        mapgen_index: 0,
+        mapgen_timer: 0.0,
+    };
+
+    // Watching levels being built is for debugging generators, so it's only on when asked for.
+    map_builders::set_record_snapshots(std::env::args().any(|arg| arg == "--show-mapgen"));
 
     // Register components
     gs.ecs.register::<Position>();
@@ -473,25 +624,38 @@ fn main() -> rltk::BError {
     gs.ecs.register::<MeleePowerBonus>();
     gs.ecs.register::<DefenseBonus>();
     gs.ecs.register::<WantsToRemoveItem>();
+    gs.ecs.register::<OtherLevelPosition>();
+    gs.ecs.register::<Hidden>();
+    gs.ecs.register::<EntryTrigger>();
+    gs.ecs.register::<EntityMoved>();
+    gs.ecs.register::<SingleActivation>();
+    gs.ecs.register::<TeleportsEntity>();
+    gs.ecs.register::<RaisesAlarm>();
+    gs.ecs.register::<Alerted>();
+    gs.ecs.register::<Swimmer>();
+    gs.ecs.register::<Slowed>();
+    gs.ecs.register::<Bystander>();
+    gs.ecs.register::<Vendor>();
+    gs.ecs.register::<GroupMember>();
+    gs.ecs.register::<Boss>();
+    gs.ecs.register::<Regeneration>();
+    gs.ecs.register::<WarCry>();
+    gs.ecs.register::<LootTable>();
 
     gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
 
-    let map = new_map_rooms_and_corridors(1);
-
-    // Spawn player in center of first room.
-    let (player_x, player_y) = map.rooms[0].center();
-    let player_entity = spawner::player(&mut gs.ecs, player_x, player_y);
-
-    // Spawn monsters in the center of other rooms.
-    gs.ecs.insert(rltk::RandomNumberGenerator::new());
-    for room in map.rooms.iter().skip(1) {
-        spawner::spawn_room(&mut gs.ecs, room, 1);
-    }
-
-    gs.ecs.insert(map);
-    gs.ecs.insert(player_entity);
+    gs.ecs.insert(Map::new(1, map::MAPWIDTH, map::MAPHEIGHT));
+    gs.ecs.insert(MasterDungeonMap::new());
+    gs.ecs.insert(SpawnedBosses::new());
+    gs.ecs.insert(GroupIds::new());
     // Add player position as a resource others can respond to.
-    gs.ecs.insert(Point::new(player_x, player_y));
+    gs.ecs.insert(Point::new(0, 0));
+    let run_seed = RunSeed::from_args().unwrap_or_else(RunSeed::random);
+    gs.ecs.insert(run_seed);
+    gs.ecs.insert(run_seed.gameplay_rng());
+    // Player is placed on the map once a new run starts.
+    let player_entity = spawner::player(&mut gs.ecs, 0, 0);
+    gs.ecs.insert(player_entity);
     gs.ecs.insert(RunState::MainMenu {
         menu_selection: gui::MainMenuSelection::NewGame,
     });
@@ -1,18 +1,77 @@
 use crate::rect::Rect;
-use rltk::{Algorithm2D, BaseMap, Point, RGB, RandomNumberGenerator, Rltk};
+use rltk::{Algorithm2D, BaseMap, Point};
 use serde::{Deserialize, Serialize};
 use specs::prelude::*;
-use std::cmp::{max, min};
 
-pub const MAPWIDTH: usize = 80;
-pub const MAPHEIGHT: usize = 43;
-pub const MAPCOUNT: usize = MAPHEIGHT * MAPWIDTH;
+/// Width of a standard level, which fits on screen without scrolling.
+pub const MAPWIDTH: i32 = 80;
+/// Height of a standard level, which fits on screen without scrolling.
+pub const MAPHEIGHT: i32 = 43;
 
 #[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum TileType {
     Wall,
     Floor,
     DownStairs,
+    UpStairs,
+    ClosedDoor,
+    OpenDoor,
+    /// Door that looks and acts like a wall until the player finds it, when it becomes a closed door.
+    SecretDoor,
+    /// Knee-deep water that slows down anything wading through it.
+    ShallowWater,
+    /// Water too deep to cross without swimming.
+    DeepWater,
+    /// Molten rock that burns anything standing in it.
+    Lava,
+    /// Fallen stone that blocks movement but not sight.
+    Rubble,
+    /// Walkway across a chasm.
+    Bridge,
+    /// Drop to the level below. Anything stepping in falls through.
+    Chasm,
+    /// Paved street in town.
+    Road,
+    /// Open ground in town.
+    Grass,
+}
+
+impl TileType {
+    /// Check if nothing can move onto the tile.
+    pub fn blocks_movement(self) -> bool {
+        matches!(
+            self,
+            TileType::Wall | TileType::SecretDoor | TileType::Rubble
+        )
+    }
+
+    /// Check if the tile blocks line of sight.
+    pub fn is_opaque(self) -> bool {
+        matches!(
+            self,
+            TileType::Wall | TileType::ClosedDoor | TileType::SecretDoor
+        )
+    }
+
+    /// Check if only swimmers can cross the tile.
+    pub fn needs_swimming(self) -> bool {
+        self == TileType::DeepWater
+    }
+
+    /// Check if moving onto the tile hurts, so monsters steer clear of it.
+    pub fn is_harmful(self) -> bool {
+        matches!(self, TileType::Lava | TileType::Chasm)
+    }
+
+    /// How many times the normal cost it is to path onto the tile.
+    pub fn movement_cost(self) -> f32 {
+        match self {
+            TileType::ShallowWater => 2.0,
+            // Only worth crossing when there's no other way around.
+            TileType::Lava => 20.0,
+            _ => 1.0,
+        }
+    }
 }
 
 #[derive(Default, Serialize, Deserialize, Clone)]
@@ -25,6 +84,8 @@ pub struct Map {
     pub visible_tiles: Vec<bool>,
     pub blocked: Vec<bool>,
     pub depth: i32,
+    /// Tile the player started the level on, to arrive on when there are no stairs to arrive on.
+    pub start_idx: usize,
 
     #[serde(skip_serializing)]
     #[serde(skip_deserializing)]
@@ -32,6 +93,23 @@ pub struct Map {
 }
 
 impl Map {
+    /// Create a new map of solid walls at the given depth and size.
+    pub fn new(new_depth: i32, width: i32, height: i32) -> Map {
+        let map_tile_count = (width * height) as usize;
+        Map {
+            tiles: vec![TileType::Wall; map_tile_count],
+            rooms: Vec::new(),
+            width,
+            height,
+            revealed_tiles: vec![false; map_tile_count],
+            visible_tiles: vec![false; map_tile_count],
+            blocked: vec![false; map_tile_count],
+            tile_content: vec![Vec::new(); map_tile_count],
+            depth: new_depth,
+            start_idx: 0,
+        }
+    }
+
     /// Clears content from all self.tile_content vectors.
     ///
     /// While this clears tile content, this has no guarantee that it will free up allocated memory.
@@ -41,15 +119,20 @@ impl Map {
         }
     }
 
-    /// Populate self.blocked with bools indicating if tile is blocked (i.e. a wall).
+    /// Populate self.blocked with bools indicating if tile is blocked (i.e. a wall, rubble, or a secret door that hasn't been found).
+    ///
+    /// Closed doors aren't blocked, so paths run through them. Anything moving into one opens it instead.
+    /// Deep water and chasms aren't blocked either, since what stops a mover there depends on the mover; they're left out of exits instead.
     pub fn populate_blocked(&mut self) {
         for (i, tile) in self.tiles.iter_mut().enumerate() {
-            self.blocked[i] = *tile == TileType::Wall;
+            self.blocked[i] = tile.blocks_movement();
         }
     }
 
     /// Check if tile at x, y is not blocking and  within the map, e.g. a wall, not an exit.
-    fn is_exit_valid(&self, x: i32, y: i32) -> bool {
+    ///
+    /// Chasms are never exits, and deep water is only an exit for swimmers.
+    fn is_exit_valid(&self, x: i32, y: i32, can_swim: bool) -> bool {
         // False if x, y is outside map bounds.
         // Need check first to prevent reading outside valid memory.
         if x < 1 || x > self.width - 1 || y < 1 || y > self.height - 1 {
@@ -57,42 +140,43 @@ impl Map {
         }
 
         let idx = self.xy_idx(x, y);
-        !self.blocked[idx]
+        let tile = self.tiles[idx];
+        !self.blocked[idx] && tile != TileType::Chasm && (can_swim || !tile.needs_swimming())
     }
 
-    /// Fetch idx for flattened vector of 2d map tiles.
-    ///
-    /// Pulls tiles from memory reading from left-to-right.
-    pub fn xy_idx(&self, x: i32, y: i32) -> usize {
-        (y as usize * self.width as usize) + x as usize
-    }
+    /// Exits from idx with their pathing costs, scaled by the terrain being moved onto.
+    fn exits(&self, idx: usize, can_swim: bool) -> rltk::SmallVec<[(usize, f32); 10]> {
+        let mut exits = rltk::SmallVec::new();
+        let x = idx as i32 % self.width;
+        let y = idx as i32 / self.width;
 
-    fn apply_room_to_map(&mut self, room: &Rect) {
-        // Set every tile in room's rect a floor.
-        for y in room.y1 + 1..=room.y2 {
-            for x in room.x1 + 1..=room.x2 {
-                let idx = self.xy_idx(x, y);
-                self.tiles[idx] = TileType::Floor;
+        let directions = [
+            // Cardinal directions
+            (-1, 0, 1.0),
+            (1, 0, 1.0),
+            (0, -1, 1.0),
+            (0, 1, 1.0),
+            // Diagonal directions
+            (-1, -1, 1.45),
+            (1, -1, 1.45),
+            (-1, 1, 1.45),
+            (1, 1, 1.45),
+        ];
+        for (dx, dy, cost) in directions {
+            if self.is_exit_valid(x + dx, y + dy, can_swim) {
+                let exit_idx = self.xy_idx(x + dx, y + dy);
+                exits.push((exit_idx, cost * self.tiles[exit_idx].movement_cost()));
             }
         }
-    }
 
-    fn apply_horizontal_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
-        for x in min(x1, x2)..=max(x1, x2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx] = TileType::Floor;
-            }
-        }
+        exits
     }
 
-    fn apply_vertical_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
-        for y in min(y1, y2)..=max(y1, y2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx] = TileType::Floor;
-            }
-        }
+    /// Fetch idx for flattened vector of 2d map tiles.
+    ///
+    /// Pulls tiles from memory reading from left-to-right.
+    pub fn xy_idx(&self, x: i32, y: i32) -> usize {
+        (y as usize * self.width as usize) + x as usize
     }
 }
 
@@ -104,44 +188,11 @@ impl Algorithm2D for Map {
 
 impl BaseMap for Map {
     fn is_opaque(&self, idx: usize) -> bool {
-        self.tiles[idx] == TileType::Wall
+        self.tiles[idx].is_opaque()
     }
 
     fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
-        let mut exits = rltk::SmallVec::new();
-        let x = idx as i32 % self.width;
-        let y = idx as i32 / self.width;
-        let w = self.width as usize;
-
-        // Cardinal directions
-        if self.is_exit_valid(x - 1, y) {
-            exits.push((idx - 1, 1.0))
-        };
-        if self.is_exit_valid(x + 1, y) {
-            exits.push((idx + 1, 1.0))
-        };
-        if self.is_exit_valid(x, y - 1) {
-            exits.push((idx - w, 1.0))
-        };
-        if self.is_exit_valid(x, y + 1) {
-            exits.push((idx + w, 1.0))
-        };
-
-        // Diagonal directions
-        if self.is_exit_valid(x - 1, y - 1) {
-            exits.push(((idx - w) - 1, 1.45))
-        };
-        if self.is_exit_valid(x + 1, y - 1) {
-            exits.push(((idx - w) + 1, 1.45))
-        };
-        if self.is_exit_valid(x - 1, y + 1) {
-            exits.push(((idx + w) - 1, 1.45))
-        };
-        if self.is_exit_valid(x + 1, y + 1) {
-            exits.push(((idx + w) + 1, 1.45))
-        };
-
-        exits
+        self.exits(idx, false)
     }
 
     fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
@@ -152,113 +203,27 @@ impl BaseMap for Map {
     }
 }
 
-/// Construct a map with random rooms and corridors.
-///
-/// Sets the maximum number of rooms and the minimum and maximum room sizes.
-pub fn new_map_rooms_and_corridors(new_depth: i32) -> Map {
-    let mut map = Map {
-        tiles: vec![TileType::Wall; MAPCOUNT],
-        rooms: Vec::new(),
-        width: MAPWIDTH as i32,
-        height: MAPHEIGHT as i32,
-        revealed_tiles: vec![false; MAPCOUNT],
-        visible_tiles: vec![false; MAPCOUNT],
-        blocked: vec![false; MAPCOUNT],
-        tile_content: vec![Vec::new(); MAPCOUNT],
-        depth: new_depth,
-    };
-
-    const MAX_ROOMS: i32 = 30;
-    const MIN_SIZE: i32 = 6;
-    const MAX_SIZE: i32 = 10;
-
-    let mut rng = RandomNumberGenerator::new();
-
-    for _ in 0..MAX_ROOMS {
-        // Generate new rng room.
-        let w = rng.range(MIN_SIZE, MAX_SIZE);
-        let h = rng.range(MIN_SIZE, MAX_SIZE);
-        let x = rng.roll_dice(1, map.width - w - 1) - 1;
-        let y = rng.roll_dice(1, map.height - h - 1) - 1;
-        let new_room = Rect::new(x, y, w, h);
-
-        // Use new rng room iff doesn't intersect with existing rooms.
-        let mut ok = true;
-        for other_room in map.rooms.iter() {
-            if new_room.intersect(other_room) {
-                ok = false
-            }
-        }
-        if ok {
-            map.apply_room_to_map(&new_room);
-
-            if !map.rooms.is_empty() {
-                // Connect new room with last room.
-                let (new_x, new_y) = new_room.center();
-                let (prev_x, prev_y) = map.rooms[map.rooms.len() - 1].center();
-
-                // RNG draw to see if start with horizontal or vertical tunnel first.
-                if rng.range(0, 2) == 1 {
-                    map.apply_horizontal_tunnel(prev_x, new_x, prev_y);
-                    map.apply_vertical_tunnel(prev_y, new_y, new_x);
-                } else {
-                    map.apply_vertical_tunnel(prev_y, new_y, prev_x);
-                    map.apply_horizontal_tunnel(prev_x, new_x, new_y);
-                }
-            }
+/// View of a map for pathing by something that can swim, letting paths cross deep water.
+pub struct SwimmingView<'a> {
+    pub map: &'a Map,
+}
 
-            map.rooms.push(new_room);
-        }
+impl Algorithm2D for SwimmingView<'_> {
+    fn dimensions(&self) -> Point {
+        self.map.dimensions()
     }
-
-    // Put stairs down in last-generated room.
-    let stairs_position = map.rooms[map.rooms.len() - 1].center();
-    let stairs_idx = map.xy_idx(stairs_position.0, stairs_position.1);
-    map.tiles[stairs_idx] = TileType::DownStairs;
-
-    map
 }
 
-/// Render map on screen.
-pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
-    let map = ecs.fetch::<Map>();
-
-    let mut y = 0;
-    let mut x = 0;
-
-    for (idx, tile) in map.tiles.iter().enumerate() {
-        // Render tile, if revealed, depending on its type.
-        if map.revealed_tiles[idx] {
-            let glyph;
-            let mut fg;
+impl BaseMap for SwimmingView<'_> {
+    fn is_opaque(&self, idx: usize) -> bool {
+        self.map.is_opaque(idx)
+    }
 
-            match tile {
-                TileType::Floor => {
-                    glyph = rltk::to_cp437('.');
-                    fg = RGB::from_f32(0.0, 0.5, 0.5);
-                }
-                TileType::Wall => {
-                    glyph = rltk::to_cp437('#');
-                    fg = RGB::from_f32(0.0, 1.0, 0.0);
-                }
-                TileType::DownStairs => {
-                    glyph = rltk::to_cp437('>');
-                    fg = RGB::from_f32(0., 1.0, 1.0);
-                }
-            }
-            // Remember, might be revealed, but not currently visible.
-            if !map.visible_tiles[idx] {
-                fg = fg.to_greyscale()
-            }
-            ctx.set(x, y, fg, RGB::from_f32(0.0, 0.0, 0.0), glyph);
-        }
+    fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
+        self.map.exits(idx, true)
+    }
 
-        // Move the coordinates
-        x += 1;
-        // Remember: Map is stored flat, row-major. Edge of screen is at MAPWIDTH, so might need to wrap.
-        if x > 79 {
-            x = 0;
-            y += 1;
-        }
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        self.map.get_pathing_distance(idx1, idx2)
     }
 }
@@ -0,0 +1,228 @@
+//! Map builder using binary space partitioning (BSP).
+
+use super::{
+    MapBuilder,
+    common::{
+        apply_horizontal_tunnel, apply_room_to_map, apply_vertical_tunnel, place_doors,
+        record_snapshot,
+    },
+};
+use crate::{Map, Position, TileType, rect::Rect, spawner};
+use rltk::RandomNumberGenerator;
+
+/// Smallest width or height a partition can be split down to.
+const MIN_LEAF_SIZE: i32 = 10;
+/// Smallest width or height of a room carved into a leaf.
+const MIN_ROOM_SIZE: i32 = 4;
+
+/// Builds maps by recursively splitting the map into leaves, carving one room per leaf, and connecting sibling partitions.
+///
+/// Every leaf is at least MIN_LEAF_SIZE and under twice that on each side, so the room count stays within fixed bounds: 15 to 28 rooms on an 80x43 map.
+pub struct BspDungeonBuilder {
+    map: Map,
+    starting_position: Position,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Map>,
+}
+
+impl MapBuilder for BspDungeonBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        &self.spawn_list
+    }
+
+    fn get_snapshot_history(&self) -> &Vec<Map> {
+        &self.history
+    }
+
+    fn take_snapshot(&mut self) {
+        record_snapshot(&mut self.history, &self.map);
+    }
+
+    fn is_room_based(&self) -> bool {
+        true
+    }
+}
+
+impl BspDungeonBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> BspDungeonBuilder {
+        BspDungeonBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        // Leave the outer edge of the map as wall.
+        let root = Rect::new(0, 0, self.map.width - 1, self.map.height - 1);
+        self.partition(root, rng);
+
+        // With no rooms there's nowhere for the stairs or the player, so leave the level for validation to reject.
+        if self.map.rooms.is_empty() {
+            return;
+        }
+
+        // Put doors where corridors run into rooms.
+        place_doors(&mut self.map, rng);
+
+        // Put stairs down in last-generated room.
+        let stairs_position = self.map.rooms[self.map.rooms.len() - 1].center();
+        let stairs_idx = self.map.xy_idx(stairs_position.0, stairs_position.1);
+        self.map.tiles[stairs_idx] = TileType::DownStairs;
+        self.take_snapshot();
+
+        // Start the player in the first room.
+        let (start_x, start_y) = self.map.rooms[0].center();
+        self.starting_position = Position {
+            x: start_x,
+            y: start_y,
+        };
+
+        // Fill every room but the player's with monsters and items.
+        for room in self.map.rooms.iter().skip(1) {
+            spawner::spawn_room(&self.map, rng, room, self.map.depth, &mut self.spawn_list);
+        }
+    }
+
+    /// Recursively split a partition until it is too small to split again, then carve a room into it.
+    ///
+    /// Returns the indices in self.map.rooms of every room carved into this partition, so the caller can connect it to its sibling.
+    fn partition(&mut self, leaf: Rect, rng: &mut RandomNumberGenerator) -> Vec<usize> {
+        let width = leaf.x2 - leaf.x1;
+        let height = leaf.y2 - leaf.y1;
+        let can_split_x = width >= MIN_LEAF_SIZE * 2;
+        let can_split_y = height >= MIN_LEAF_SIZE * 2;
+
+        if !can_split_x && !can_split_y {
+            return vec![self.carve_room(leaf, rng)];
+        }
+
+        // Split across the longer side so leaves stay roughly square.
+        let split_x = if can_split_x && can_split_y {
+            if width as f32 / height as f32 >= 1.25 {
+                true
+            } else if height as f32 / width as f32 >= 1.25 {
+                false
+            } else {
+                rng.range(0, 2) == 1
+            }
+        } else {
+            can_split_x
+        };
+
+        let (first, second) = if split_x {
+            let split = leaf.x1 + rng.range(MIN_LEAF_SIZE, width - MIN_LEAF_SIZE + 1);
+            (
+                Rect::new(leaf.x1, leaf.y1, split - leaf.x1, height),
+                Rect::new(split, leaf.y1, leaf.x2 - split, height),
+            )
+        } else {
+            let split = leaf.y1 + rng.range(MIN_LEAF_SIZE, height - MIN_LEAF_SIZE + 1);
+            (
+                Rect::new(leaf.x1, leaf.y1, width, split - leaf.y1),
+                Rect::new(leaf.x1, split, width, leaf.y2 - split),
+            )
+        };
+
+        let mut rooms = self.partition(first, rng);
+        let second_rooms = self.partition(second, rng);
+        self.connect_siblings(&rooms, &second_rooms, rng);
+        rooms.extend(second_rooms);
+        rooms
+    }
+
+    /// Carve a randomly sized room inside a leaf, keeping a wall between it and neighboring leaves.
+    fn carve_room(&mut self, leaf: Rect, rng: &mut RandomNumberGenerator) -> usize {
+        let leaf_width = leaf.x2 - leaf.x1;
+        let leaf_height = leaf.y2 - leaf.y1;
+
+        let w = rng.range(MIN_ROOM_SIZE, leaf_width);
+        let h = rng.range(MIN_ROOM_SIZE, leaf_height);
+        let x = leaf.x1 + rng.range(0, leaf_width - w);
+        let y = leaf.y1 + rng.range(0, leaf_height - h);
+        let room = Rect::new(x, y, w, h);
+
+        apply_room_to_map(&mut self.map, &room);
+        self.map.rooms.push(room);
+        self.take_snapshot();
+        self.map.rooms.len() - 1
+    }
+
+    /// Join two sibling partitions with a corridor between their closest pair of rooms.
+    fn connect_siblings(
+        &mut self,
+        first: &[usize],
+        second: &[usize],
+        rng: &mut RandomNumberGenerator,
+    ) {
+        let mut closest: Option<(usize, usize, i32)> = None;
+        for a in first.iter() {
+            for b in second.iter() {
+                let (ax, ay) = self.map.rooms[*a].center();
+                let (bx, by) = self.map.rooms[*b].center();
+                let distance = i32::abs(ax - bx) + i32::abs(ay - by);
+                if closest.is_none_or(|c| distance < c.2) {
+                    closest = Some((*a, *b, distance));
+                }
+            }
+        }
+
+        if let Some((a, b, _)) = closest {
+            let (prev_x, prev_y) = self.map.rooms[a].center();
+            let (new_x, new_y) = self.map.rooms[b].center();
+
+            // RNG draw to see if start with horizontal or vertical tunnel first.
+            if rng.range(0, 2) == 1 {
+                apply_horizontal_tunnel(&mut self.map, prev_x, new_x, prev_y);
+                apply_vertical_tunnel(&mut self.map, prev_y, new_y, new_x);
+            } else {
+                apply_vertical_tunnel(&mut self.map, prev_y, new_y, prev_x);
+                apply_horizontal_tunnel(&mut self.map, prev_x, new_x, new_y);
+            }
+            self.take_snapshot();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{MAPHEIGHT, MAPWIDTH};
+
+    #[test]
+    fn room_count_stays_within_bounds() {
+        // The partitioned area, inside the outer wall.
+        let width = MAPWIDTH - 1;
+        let height = MAPHEIGHT - 1;
+        let largest_leaf = MIN_LEAF_SIZE * 2 - 1;
+        let fewest_rooms = ((width + largest_leaf - 1) / largest_leaf)
+            * ((height + largest_leaf - 1) / largest_leaf);
+        let most_rooms = (width / MIN_LEAF_SIZE) * (height / MIN_LEAF_SIZE);
+        assert_eq!((fewest_rooms, most_rooms), (15, 28));
+
+        for seed in 1..=50 {
+            let mut builder = BspDungeonBuilder::new(1, MAPWIDTH, MAPHEIGHT);
+            builder.build_map(&mut RandomNumberGenerator::seeded(seed));
+            let rooms = builder.map.rooms.len() as i32;
+            assert!(
+                (fewest_rooms..=most_rooms).contains(&rooms),
+                "seed {} carved {} rooms",
+                seed,
+                rooms
+            );
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Map builder for natural-looking caves using cellular automata.
+
+use super::{
+    MapBuilder,
+    common::{
+        generate_voronoi_areas, record_snapshot, remove_unreachable_areas_returning_most_distant,
+    },
+};
+use crate::{Map, Position, TileType, spawner};
+use rltk::RandomNumberGenerator;
+
+/// Number of smoothing passes run over the random noise.
+const SMOOTHING_ITERATIONS: i32 = 15;
+
+/// Builds cave levels by seeding random walls and repeatedly smoothing them with cellular automata rules.
+pub struct CellularAutomataBuilder {
+    map: Map,
+    starting_position: Position,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Map>,
+}
+
+impl MapBuilder for CellularAutomataBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        &self.spawn_list
+    }
+
+    fn get_snapshot_history(&self) -> &Vec<Map> {
+        &self.history
+    }
+
+    fn take_snapshot(&mut self) {
+        record_snapshot(&mut self.history, &self.map);
+    }
+}
+
+impl CellularAutomataBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> CellularAutomataBuilder {
+        CellularAutomataBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        // Seed the map with roughly 55% floor, leaving the outer edge as wall.
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                let roll = rng.roll_dice(1, 100);
+                let idx = self.map.xy_idx(x, y);
+                if roll > 55 {
+                    self.map.tiles[idx] = TileType::Floor
+                } else {
+                    self.map.tiles[idx] = TileType::Wall
+                }
+            }
+        }
+
+        self.take_snapshot();
+
+        for _ in 0..SMOOTHING_ITERATIONS {
+            self.smooth();
+            self.take_snapshot();
+        }
+
+        // Start the player at the floor tile nearest the middle of the map.
+        let center = Position {
+            x: self.map.width / 2,
+            y: self.map.height / 2,
+        };
+        let start_idx = self
+            .map
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| **tile == TileType::Floor)
+            .map(|(idx, _)| idx)
+            .min_by_key(|idx| {
+                let x = *idx as i32 % self.map.width;
+                let y = *idx as i32 / self.map.width;
+                i32::abs(x - center.x) + i32::abs(y - center.y)
+            });
+        let Some(start_idx) = start_idx else {
+            // Smoothing left no floor at all; hand back the empty level for validation to reject.
+            return;
+        };
+        self.starting_position = Position {
+            x: start_idx as i32 % self.map.width,
+            y: start_idx as i32 / self.map.width,
+        };
+
+        // Put stairs down as far from the player as they can reach.
+        let exit_tile = remove_unreachable_areas_returning_most_distant(&mut self.map, start_idx);
+        self.map.tiles[exit_tile] = TileType::DownStairs;
+        self.take_snapshot();
+
+        // Fill each spawn area with monsters and items.
+        let spawn_areas = generate_voronoi_areas(&self.map, rng);
+        for area in spawn_areas.iter() {
+            spawner::spawn_region(rng, area, self.map.depth, &mut self.spawn_list);
+        }
+    }
+
+    /// Run one cellular automata pass: tiles with many neighboring walls (or none at all) become walls, everything else becomes floor.
+    fn smooth(&mut self) {
+        let mut newtiles = self.map.tiles.clone();
+        let w = self.map.width as usize;
+
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                let idx = self.map.xy_idx(x, y);
+                let neighbors = [
+                    idx - 1,
+                    idx + 1,
+                    idx - w,
+                    idx + w,
+                    idx - w - 1,
+                    idx - w + 1,
+                    idx + w - 1,
+                    idx + w + 1,
+                ]
+                .iter()
+                .filter(|n| self.map.tiles[**n] == TileType::Wall)
+                .count();
+
+                if neighbors > 4 || neighbors == 0 {
+                    newtiles[idx] = TileType::Wall;
+                } else {
+                    newtiles[idx] = TileType::Floor;
+                }
+            }
+        }
+
+        self.map.tiles = newtiles;
+    }
+}
@@ -0,0 +1,303 @@
+//! Helpers shared between map builders.
+
+use crate::{Map, TileType, rect::Rect};
+use rltk::{BaseMap, DistanceAlg, Point, RandomNumberGenerator};
+use std::cmp::{max, min};
+use std::collections::VecDeque;
+
+/// Add a copy of the map to a builder's snapshot history, if snapshots are being recorded.
+pub fn record_snapshot(history: &mut Vec<Map>, map: &Map) {
+    if super::is_recording_snapshots() {
+        history.push(map.clone());
+    }
+}
+
+/// Set every tile in a room's rect to a floor.
+pub fn apply_room_to_map(map: &mut Map, room: &Rect) {
+    for y in room.y1 + 1..=room.y2 {
+        for x in room.x1 + 1..=room.x2 {
+            let idx = map.xy_idx(x, y);
+            map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+/// Carve a horizontal tunnel of floor tiles along row y.
+pub fn apply_horizontal_tunnel(map: &mut Map, x1: i32, x2: i32, y: i32) {
+    for x in min(x1, x2)..=max(x1, x2) {
+        let idx = map.xy_idx(x, y);
+        if idx > 0 && idx < map.width as usize * map.height as usize {
+            map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+/// Carve a vertical tunnel of floor tiles along column x.
+pub fn apply_vertical_tunnel(map: &mut Map, y1: i32, y2: i32, x: i32) {
+    for y in min(y1, y2)..=max(y1, y2) {
+        let idx = map.xy_idx(x, y);
+        if idx > 0 && idx < map.width as usize * map.height as usize {
+            map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+/// One in this many doors is placed as a secret door.
+const SECRET_DOOR_CHANCE: i32 = 8;
+
+/// Place closed doors in the doorways where corridors enter the map's rooms, occasionally hiding one as a secret door.
+///
+/// A doorway is a floor tile on a room's outer wall with walls on both sides of it, so corridors running alongside a room don't get doors.
+pub fn place_doors(map: &mut Map, rng: &mut RandomNumberGenerator) {
+    let rooms = map.rooms.clone();
+    for room in rooms.iter() {
+        let mut outer_wall: Vec<(i32, i32)> = Vec::new();
+        for x in room.x1 + 1..=room.x2 {
+            outer_wall.push((x, room.y1));
+            outer_wall.push((x, room.y2 + 1));
+        }
+        for y in room.y1 + 1..=room.y2 {
+            outer_wall.push((room.x1, y));
+            outer_wall.push((room.x2 + 1, y));
+        }
+
+        for (x, y) in outer_wall {
+            if is_doorway(map, x, y) {
+                let idx = map.xy_idx(x, y);
+                map.tiles[idx] = if rng.roll_dice(1, SECRET_DOOR_CHANCE) == 1 {
+                    TileType::SecretDoor
+                } else {
+                    TileType::ClosedDoor
+                };
+            }
+        }
+    }
+}
+
+/// Check if x, y is floor squeezed between two walls, either side to side or top to bottom, with no door next to it.
+fn is_doorway(map: &Map, x: i32, y: i32) -> bool {
+    if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 {
+        return false;
+    }
+    let tile = |x: i32, y: i32| map.tiles[map.xy_idx(x, y)];
+    if tile(x, y) != TileType::Floor {
+        return false;
+    }
+
+    let neighbors = [
+        tile(x - 1, y),
+        tile(x + 1, y),
+        tile(x, y - 1),
+        tile(x, y + 1),
+    ];
+    if neighbors.contains(&TileType::ClosedDoor) || neighbors.contains(&TileType::SecretDoor) {
+        return false;
+    }
+    let [west, east, north, south] = neighbors.map(|t| t == TileType::Wall);
+    (west && east && !north && !south) || (north && south && !west && !east)
+}
+
+/// Populate map.blocked for connectivity checks while building.
+///
+/// Unlike Map::populate_blocked, secret doors are left open: the player can always search for them, so a level is still connected if part of it is only reachable through one.
+pub fn populate_blocked_for_generation(map: &mut Map) {
+    map.populate_blocked();
+    for (i, tile) in map.tiles.iter().enumerate() {
+        if *tile == TileType::SecretDoor {
+            map.blocked[i] = false;
+        }
+    }
+}
+
+/// Wall off every tile that can't be reached from start_idx and return the index of the reachable tile farthest from it.
+///
+/// Uses a Dijkstra flood fill over the map's exits, treating secret doors as passable.
+pub fn remove_unreachable_areas_returning_most_distant(map: &mut Map, start_idx: usize) -> usize {
+    populate_blocked_for_generation(map);
+    let map_starts: Vec<usize> = vec![start_idx];
+    let dijkstra_map = rltk::DijkstraMap::new(
+        map.width as usize,
+        map.height as usize,
+        &map_starts,
+        map,
+        // Deep enough to flood any map, even a winding one many screens across.
+        (map.width * map.height) as f32,
+    );
+
+    let mut exit_tile = (0, 0.0f32);
+    for (i, tile) in map.tiles.iter_mut().enumerate() {
+        if *tile == TileType::Floor {
+            let distance_to_start = dijkstra_map.map[i];
+            if distance_to_start == f32::MAX {
+                // Can't get to this tile, so wall it off.
+                *tile = TileType::Wall;
+            } else if distance_to_start > exit_tile.1 {
+                exit_tile = (i, distance_to_start);
+            }
+        }
+    }
+    map.populate_blocked();
+
+    exit_tile.0
+}
+
+/// Roughly how many floor tiles each spawn area covers, about the floor space of a room on room-based levels.
+const FLOOR_TILES_PER_SPAWN_AREA: usize = 120;
+
+/// Split the map's floor tiles into Voronoi spawn areas: scatter seed points over the floor and give every floor tile to its nearest seed.
+///
+/// The number of seeds scales with the amount of floor, so maps without rooms get about as many spawn areas as a room-based level has rooms.
+/// Useful for maps without rooms to hand to spawner::spawn_region.
+pub fn generate_voronoi_areas(map: &Map, rng: &mut RandomNumberGenerator) -> Vec<Vec<usize>> {
+    let floor_tiles: Vec<usize> = map
+        .tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, tile)| **tile == TileType::Floor)
+        .map(|(idx, _)| idx)
+        .collect();
+    if floor_tiles.is_empty() {
+        return Vec::new();
+    }
+
+    let n_seeds = usize::max(1, floor_tiles.len() / FLOOR_TILES_PER_SPAWN_AREA);
+    let mut seeds: Vec<Point> = Vec::new();
+    while seeds.len() < n_seeds {
+        let idx = floor_tiles[(rng.roll_dice(1, floor_tiles.len() as i32) - 1) as usize];
+        let seed = Point::new(idx as i32 % map.width, idx as i32 / map.width);
+        if !seeds.contains(&seed) {
+            seeds.push(seed);
+        }
+    }
+
+    let mut areas: Vec<Vec<usize>> = vec![Vec::new(); n_seeds];
+    for idx in floor_tiles {
+        let tile = Point::new(idx as i32 % map.width, idx as i32 / map.width);
+        let nearest = seeds
+            .iter()
+            .map(|seed| DistanceAlg::PythagorasSquared.distance2d(tile, *seed))
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(seed, _)| seed)
+            .unwrap();
+        areas[nearest].push(idx);
+    }
+    areas
+}
+
+/// Mirroring applied when painting floor onto a map.
+#[derive(PartialEq, Copy, Clone)]
+pub enum Symmetry {
+    None,
+    Horizontal,
+    Vertical,
+    Both,
+}
+
+/// Paint floor with a square brush at x, y, mirrored across the middle of the map according to the symmetry mode.
+pub fn paint(map: &mut Map, mode: Symmetry, brush_size: i32, x: i32, y: i32) {
+    let center_x = map.width / 2;
+    let center_y = map.height / 2;
+    let dist_x = i32::abs(center_x - x);
+    let dist_y = i32::abs(center_y - y);
+
+    match mode {
+        Symmetry::None => apply_paint(map, brush_size, x, y),
+        Symmetry::Horizontal => {
+            apply_paint(map, brush_size, center_x + dist_x, y);
+            apply_paint(map, brush_size, center_x - dist_x, y);
+        }
+        Symmetry::Vertical => {
+            apply_paint(map, brush_size, x, center_y + dist_y);
+            apply_paint(map, brush_size, x, center_y - dist_y);
+        }
+        Symmetry::Both => {
+            apply_paint(map, brush_size, center_x + dist_x, center_y + dist_y);
+            apply_paint(map, brush_size, center_x - dist_x, center_y + dist_y);
+            apply_paint(map, brush_size, center_x + dist_x, center_y - dist_y);
+            apply_paint(map, brush_size, center_x - dist_x, center_y - dist_y);
+        }
+    }
+}
+
+/// Set the tiles under a square brush centered on x, y to floor, leaving the outer edge of the map as wall.
+fn apply_paint(map: &mut Map, brush_size: i32, x: i32, y: i32) {
+    let half_brush_size = brush_size / 2;
+    for brush_y in y - half_brush_size..=y + half_brush_size - (1 - brush_size % 2) {
+        for brush_x in x - half_brush_size..=x + half_brush_size - (1 - brush_size % 2) {
+            if brush_x > 0 && brush_x < map.width - 1 && brush_y > 0 && brush_y < map.height - 1 {
+                let idx = map.xy_idx(brush_x, brush_y);
+                map.tiles[idx] = TileType::Floor;
+            }
+        }
+    }
+}
+
+/// Count the floor tiles on the map.
+pub fn count_floor_tiles(map: &Map) -> usize {
+    map.tiles
+        .iter()
+        .filter(|tile| **tile == TileType::Floor)
+        .count()
+}
+
+/// Check if the player could end up standing on a tile, so it counts towards connectivity.
+pub fn is_standable(tile: TileType) -> bool {
+    !matches!(
+        tile,
+        TileType::Wall | TileType::Rubble | TileType::DeepWater | TileType::Chasm
+    )
+}
+
+/// Number of steps it takes to walk from start_idx to every tile, or None for tiles that can't be reached.
+///
+/// Uses a breadth-first search over BaseMap::get_available_exits, so map.blocked must be populated first, usually with populate_blocked_for_generation.
+pub fn walking_distances(map: &Map, start_idx: usize) -> Vec<Option<usize>> {
+    let mut distances: Vec<Option<usize>> = vec![None; map.tiles.len()];
+    let mut open_list: VecDeque<usize> = VecDeque::new();
+    distances[start_idx] = Some(0);
+    open_list.push_back(start_idx);
+    while let Some(idx) = open_list.pop_front() {
+        let steps = distances[idx].unwrap_or(0) + 1;
+        for (exit_idx, _cost) in map.get_available_exits(idx).iter() {
+            if distances[*exit_idx].is_none() {
+                distances[*exit_idx] = Some(steps);
+                open_list.push_back(*exit_idx);
+            }
+        }
+    }
+    distances
+}
+
+/// Tile to put the stairs back up on: the start itself if it's plain floor, or else the nearest floor tile walkable from it.
+///
+/// Returns None if there's no floor within walking distance of the start.
+pub fn up_stairs_idx(map: &Map, start_idx: usize) -> Option<usize> {
+    if map.tiles[start_idx] == TileType::Floor {
+        return Some(start_idx);
+    }
+
+    let mut walkable = map.clone();
+    populate_blocked_for_generation(&mut walkable);
+    let distances = walking_distances(&walkable, start_idx);
+    walkable
+        .tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, tile)| **tile == TileType::Floor)
+        .filter_map(|(idx, _)| distances[idx].map(|steps| (steps, idx)))
+        .min()
+        .map(|(_, idx)| idx)
+}
+
+/// Count the floor tiles that can't be reached from start_idx by walking the map's exits.
+///
+/// As with walking_distances, map.blocked must be populated first.
+pub fn count_unreachable_floor_tiles(map: &Map, start_idx: usize) -> usize {
+    let distances = walking_distances(map, start_idx);
+    map.tiles
+        .iter()
+        .enumerate()
+        .filter(|(idx, tile)| is_standable(**tile) && distances[*idx].is_none())
+        .count()
+}
@@ -0,0 +1,299 @@
+//! Map builder using diffusion-limited aggregation (DLA).
+
+use super::{
+    MapBuilder,
+    common::{
+        Symmetry, count_floor_tiles, generate_voronoi_areas, paint, record_snapshot,
+        remove_unreachable_areas_returning_most_distant,
+    },
+};
+use crate::{Map, Position, TileType, spawner};
+use rltk::RandomNumberGenerator;
+
+/// Number of walkers to let stick between generation snapshots, since each only adds a tile or so.
+const WALKERS_PER_SNAPSHOT: i32 = 10;
+
+/// How walkers move before they stick to the growing floor.
+#[derive(PartialEq, Copy, Clone)]
+pub enum DLAAlgorithm {
+    /// Walkers start anywhere and wander until they bump into floor.
+    WalkInwards,
+    /// Walkers start at the middle and wander until they dig into wall.
+    WalkOutwards,
+    /// Walkers start anywhere and head straight for the middle until they bump into floor.
+    CentralAttractor,
+}
+
+/// Builds maps by growing floor outward from the middle, one random walker at a time.
+pub struct DLABuilder {
+    map: Map,
+    starting_position: Position,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Map>,
+    algorithm: DLAAlgorithm,
+    brush_size: i32,
+    symmetry: Symmetry,
+    floor_percent: f32,
+}
+
+impl MapBuilder for DLABuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        &self.spawn_list
+    }
+
+    fn get_snapshot_history(&self) -> &Vec<Map> {
+        &self.history
+    }
+
+    fn take_snapshot(&mut self) {
+        record_snapshot(&mut self.history, &self.map);
+    }
+}
+
+impl DLABuilder {
+    pub fn new(
+        new_depth: i32,
+        width: i32,
+        height: i32,
+        algorithm: DLAAlgorithm,
+        brush_size: i32,
+        symmetry: Symmetry,
+        floor_percent: f32,
+    ) -> DLABuilder {
+        DLABuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+            algorithm,
+            brush_size,
+            symmetry,
+            floor_percent,
+        }
+    }
+
+    pub fn walk_inwards(new_depth: i32, width: i32, height: i32) -> DLABuilder {
+        DLABuilder::new(
+            new_depth,
+            width,
+            height,
+            DLAAlgorithm::WalkInwards,
+            1,
+            Symmetry::None,
+            0.25,
+        )
+    }
+
+    pub fn walk_outwards(new_depth: i32, width: i32, height: i32) -> DLABuilder {
+        DLABuilder::new(
+            new_depth,
+            width,
+            height,
+            DLAAlgorithm::WalkOutwards,
+            2,
+            Symmetry::None,
+            0.25,
+        )
+    }
+
+    pub fn central_attractor(new_depth: i32, width: i32, height: i32) -> DLABuilder {
+        DLABuilder::new(
+            new_depth,
+            width,
+            height,
+            DLAAlgorithm::CentralAttractor,
+            2,
+            Symmetry::None,
+            0.25,
+        )
+    }
+
+    /// Bug-like level mirrored left to right.
+    pub fn insectoid(new_depth: i32, width: i32, height: i32) -> DLABuilder {
+        DLABuilder::new(
+            new_depth,
+            width,
+            height,
+            DLAAlgorithm::CentralAttractor,
+            2,
+            Symmetry::Horizontal,
+            0.25,
+        )
+    }
+
+    /// Crystal-like level mirrored across both axes.
+    pub fn crystal(new_depth: i32, width: i32, height: i32) -> DLABuilder {
+        DLABuilder::new(
+            new_depth,
+            width,
+            height,
+            DLAAlgorithm::WalkInwards,
+            1,
+            Symmetry::Both,
+            0.3,
+        )
+    }
+
+    /// Tall level mirrored top to bottom.
+    pub fn totem(new_depth: i32, width: i32, height: i32) -> DLABuilder {
+        DLABuilder::new(
+            new_depth,
+            width,
+            height,
+            DLAAlgorithm::CentralAttractor,
+            2,
+            Symmetry::Vertical,
+            0.25,
+        )
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        // Seed a small cross of floor in the middle of the map for walkers to stick to.
+        self.starting_position = Position {
+            x: self.map.width / 2,
+            y: self.map.height / 2,
+        };
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        let w = self.map.width as usize;
+        self.map.tiles[start_idx] = TileType::Floor;
+        self.map.tiles[start_idx - 1] = TileType::Floor;
+        self.map.tiles[start_idx + 1] = TileType::Floor;
+        self.map.tiles[start_idx - w] = TileType::Floor;
+        self.map.tiles[start_idx + w] = TileType::Floor;
+
+        let total_tiles = self.map.width * self.map.height;
+        let desired_floor_tiles = (self.floor_percent * total_tiles as f32) as usize;
+        let mut walkers = 0;
+        while count_floor_tiles(&self.map) < desired_floor_tiles {
+            match self.algorithm {
+                DLAAlgorithm::WalkInwards => self.walk_inwards_once(rng),
+                DLAAlgorithm::WalkOutwards => self.walk_outwards_once(rng),
+                DLAAlgorithm::CentralAttractor => self.central_attractor_once(rng),
+            }
+            walkers += 1;
+            if walkers % WALKERS_PER_SNAPSHOT == 0 {
+                self.take_snapshot();
+            }
+        }
+
+        // Put stairs down as far from the player as they can reach.
+        let exit_tile = remove_unreachable_areas_returning_most_distant(&mut self.map, start_idx);
+        self.map.tiles[exit_tile] = TileType::DownStairs;
+        self.take_snapshot();
+
+        // Fill each spawn area with monsters and items.
+        let spawn_areas = generate_voronoi_areas(&self.map, rng);
+        for area in spawn_areas.iter() {
+            spawner::spawn_region(rng, area, self.map.depth, &mut self.spawn_list);
+        }
+    }
+
+    /// Move one step in a random direction, staying off the outer edge of the map.
+    fn stumble(&self, x: &mut i32, y: &mut i32, rng: &mut RandomNumberGenerator) {
+        match rng.roll_dice(1, 4) {
+            1 => {
+                if *x > 2 {
+                    *x -= 1;
+                }
+            }
+            2 => {
+                if *x < self.map.width - 2 {
+                    *x += 1;
+                }
+            }
+            3 => {
+                if *y > 2 {
+                    *y -= 1;
+                }
+            }
+            _ => {
+                if *y < self.map.height - 2 {
+                    *y += 1;
+                }
+            }
+        }
+    }
+
+    fn walk_inwards_once(&mut self, rng: &mut RandomNumberGenerator) {
+        let mut digger_x = rng.roll_dice(1, self.map.width - 3) + 1;
+        let mut digger_y = rng.roll_dice(1, self.map.height - 3) + 1;
+        let mut prev_x = digger_x;
+        let mut prev_y = digger_y;
+        let mut digger_idx = self.map.xy_idx(digger_x, digger_y);
+        while self.map.tiles[digger_idx] == TileType::Wall {
+            prev_x = digger_x;
+            prev_y = digger_y;
+            self.stumble(&mut digger_x, &mut digger_y, rng);
+            digger_idx = self.map.xy_idx(digger_x, digger_y);
+        }
+        paint(
+            &mut self.map,
+            self.symmetry,
+            self.brush_size,
+            prev_x,
+            prev_y,
+        );
+    }
+
+    fn walk_outwards_once(&mut self, rng: &mut RandomNumberGenerator) {
+        let mut digger_x = self.starting_position.x;
+        let mut digger_y = self.starting_position.y;
+        let mut digger_idx = self.map.xy_idx(digger_x, digger_y);
+        while self.map.tiles[digger_idx] == TileType::Floor {
+            self.stumble(&mut digger_x, &mut digger_y, rng);
+            digger_idx = self.map.xy_idx(digger_x, digger_y);
+        }
+        paint(
+            &mut self.map,
+            self.symmetry,
+            self.brush_size,
+            digger_x,
+            digger_y,
+        );
+    }
+
+    fn central_attractor_once(&mut self, rng: &mut RandomNumberGenerator) {
+        let mut digger_x = rng.roll_dice(1, self.map.width - 3) + 1;
+        let mut digger_y = rng.roll_dice(1, self.map.height - 3) + 1;
+        let mut prev_x = digger_x;
+        let mut prev_y = digger_y;
+        let mut digger_idx = self.map.xy_idx(digger_x, digger_y);
+
+        let path = rltk::line2d(
+            rltk::LineAlg::Bresenham,
+            rltk::Point::new(digger_x, digger_y),
+            rltk::Point::new(self.starting_position.x, self.starting_position.y),
+        );
+        for step in path.iter() {
+            if self.map.tiles[digger_idx] != TileType::Wall {
+                break;
+            }
+            prev_x = digger_x;
+            prev_y = digger_y;
+            digger_x = step.x;
+            digger_y = step.y;
+            digger_idx = self.map.xy_idx(digger_x, digger_y);
+        }
+        paint(
+            &mut self.map,
+            self.symmetry,
+            self.brush_size,
+            prev_x,
+            prev_y,
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Map builder for organic tunnels carved by drunkard's walk diggers.
+
+use super::{
+    MapBuilder,
+    common::{
+        count_floor_tiles, generate_voronoi_areas, record_snapshot,
+        remove_unreachable_areas_returning_most_distant,
+    },
+};
+use crate::{Map, Position, TileType, spawner};
+use rltk::RandomNumberGenerator;
+
+/// Where each new digger starts walking from.
+#[derive(PartialEq, Copy, Clone)]
+pub enum DrunkSpawnMode {
+    /// Every digger starts at the player's starting position.
+    StartingPoint,
+    /// Each digger starts at a random floor tile that has already been dug.
+    Random,
+}
+
+/// Settings for a drunkard's walk map builder.
+pub struct DrunkardSettings {
+    pub spawn_mode: DrunkSpawnMode,
+    /// Number of steps a digger takes before it passes out.
+    pub drunken_lifetime: i32,
+    /// Fraction of the map that should be floor before digging stops.
+    pub floor_percent: f32,
+    /// Maximum number of diggers to send out, in case the floor target can't be reached.
+    pub max_walkers: i32,
+}
+
+/// Builds maps by sending out diggers that stumble randomly, carving floor as they go.
+pub struct DrunkardsWalkBuilder {
+    map: Map,
+    starting_position: Position,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Map>,
+    settings: DrunkardSettings,
+}
+
+impl MapBuilder for DrunkardsWalkBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        &self.spawn_list
+    }
+
+    fn get_snapshot_history(&self) -> &Vec<Map> {
+        &self.history
+    }
+
+    fn take_snapshot(&mut self) {
+        record_snapshot(&mut self.history, &self.map);
+    }
+}
+
+impl DrunkardsWalkBuilder {
+    pub fn new(
+        new_depth: i32,
+        width: i32,
+        height: i32,
+        settings: DrunkardSettings,
+    ) -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+            settings,
+        }
+    }
+
+    /// Big open cave around the starting point.
+    pub fn open_area(new_depth: i32, width: i32, height: i32) -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder::new(
+            new_depth,
+            width,
+            height,
+            DrunkardSettings {
+                spawn_mode: DrunkSpawnMode::StartingPoint,
+                drunken_lifetime: 400,
+                floor_percent: 0.5,
+                max_walkers: 200,
+            },
+        )
+    }
+
+    /// Open halls scattered across the whole map.
+    pub fn open_halls(new_depth: i32, width: i32, height: i32) -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder::new(
+            new_depth,
+            width,
+            height,
+            DrunkardSettings {
+                spawn_mode: DrunkSpawnMode::Random,
+                drunken_lifetime: 400,
+                floor_percent: 0.5,
+                max_walkers: 200,
+            },
+        )
+    }
+
+    /// Many short-lived diggers, leaving narrow twisting passages.
+    pub fn winding_passages(new_depth: i32, width: i32, height: i32) -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder::new(
+            new_depth,
+            width,
+            height,
+            DrunkardSettings {
+                spawn_mode: DrunkSpawnMode::Random,
+                drunken_lifetime: 100,
+                floor_percent: 0.4,
+                max_walkers: 1000,
+            },
+        )
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        // Start the player in the middle of the map.
+        self.starting_position = Position {
+            x: self.map.width / 2,
+            y: self.map.height / 2,
+        };
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        self.map.tiles[start_idx] = TileType::Floor;
+
+        let total_tiles = self.map.width * self.map.height;
+        let desired_floor_tiles = (self.settings.floor_percent * total_tiles as f32) as usize;
+        let mut digger_count = 0;
+        while count_floor_tiles(&self.map) < desired_floor_tiles
+            && digger_count < self.settings.max_walkers
+        {
+            let (mut drunk_x, mut drunk_y) = match self.settings.spawn_mode {
+                DrunkSpawnMode::StartingPoint => {
+                    (self.starting_position.x, self.starting_position.y)
+                }
+                DrunkSpawnMode::Random => {
+                    // Starting on existing floor keeps every digger's tunnels connected to the start.
+                    let floor_tiles: Vec<usize> = self
+                        .map
+                        .tiles
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, tile)| **tile == TileType::Floor)
+                        .map(|(idx, _)| idx)
+                        .collect();
+                    let idx =
+                        floor_tiles[(rng.roll_dice(1, floor_tiles.len() as i32) - 1) as usize];
+                    (idx as i32 % self.map.width, idx as i32 / self.map.width)
+                }
+            };
+
+            let mut drunk_life = self.settings.drunken_lifetime;
+            while drunk_life > 0 {
+                let drunk_idx = self.map.xy_idx(drunk_x, drunk_y);
+                self.map.tiles[drunk_idx] = TileType::Floor;
+
+                // Stumble one step in a random direction, staying off the outer edge.
+                match rng.roll_dice(1, 4) {
+                    1 => {
+                        if drunk_x > 2 {
+                            drunk_x -= 1;
+                        }
+                    }
+                    2 => {
+                        if drunk_x < self.map.width - 2 {
+                            drunk_x += 1;
+                        }
+                    }
+                    3 => {
+                        if drunk_y > 2 {
+                            drunk_y -= 1;
+                        }
+                    }
+                    _ => {
+                        if drunk_y < self.map.height - 2 {
+                            drunk_y += 1;
+                        }
+                    }
+                }
+
+                drunk_life -= 1;
+            }
+
+            digger_count += 1;
+            self.take_snapshot();
+        }
+
+        // Put stairs down as far from the player as they can reach.
+        let exit_tile = remove_unreachable_areas_returning_most_distant(&mut self.map, start_idx);
+        self.map.tiles[exit_tile] = TileType::DownStairs;
+        self.take_snapshot();
+
+        // Fill each spawn area with monsters and items.
+        let spawn_areas = generate_voronoi_areas(&self.map, rng);
+        for area in spawn_areas.iter() {
+            spawner::spawn_region(rng, area, self.map.depth, &mut self.spawn_list);
+        }
+    }
+}
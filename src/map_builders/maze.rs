@@ -0,0 +1,215 @@
+//! Map builder for labyrinth levels using a recursive backtracker maze.
+
+use super::{
+    MapBuilder,
+    common::{generate_voronoi_areas, record_snapshot},
+};
+use crate::{Map, Position, TileType, spawner};
+use rltk::RandomNumberGenerator;
+
+/// Offsets to the neighboring cell above, right, below, and left.
+const DIRECTIONS: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+/// Number of cells to carve between generation snapshots.
+const CELLS_PER_SNAPSHOT: i32 = 10;
+
+/// Builds a maze filling the whole map, with the player in one corner and the stairs in the opposite one.
+///
+/// The maze is laid out on a grid of cells two tiles apart, so the tiles between cells are the maze walls.
+pub struct MazeBuilder {
+    map: Map,
+    starting_position: Position,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Map>,
+    /// Number of extra walls knocked through after carving, adding loops to an otherwise perfect maze.
+    extra_loops: i32,
+}
+
+impl MapBuilder for MazeBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        &self.spawn_list
+    }
+
+    fn get_snapshot_history(&self) -> &Vec<Map> {
+        &self.history
+    }
+
+    fn take_snapshot(&mut self) {
+        record_snapshot(&mut self.history, &self.map);
+    }
+}
+
+impl MazeBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32, extra_loops: i32) -> MazeBuilder {
+        MazeBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+            extra_loops,
+        }
+    }
+
+    /// Perfect maze, with exactly one path between any two tiles.
+    pub fn perfect(new_depth: i32, width: i32, height: i32) -> MazeBuilder {
+        MazeBuilder::new(new_depth, width, height, 0)
+    }
+
+    /// Maze with a few loops knocked through so there is more than one way around.
+    pub fn labyrinth(new_depth: i32, width: i32, height: i32) -> MazeBuilder {
+        MazeBuilder::new(new_depth, width, height, 20)
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        let grid_width = (self.map.width - 2) / 2;
+        let grid_height = (self.map.height - 2) / 2;
+
+        self.carve_maze(grid_width, grid_height, rng);
+        self.knock_through_loops(grid_width, grid_height, rng);
+
+        // Start the player in the top-left cell and put the stairs down in the bottom-right one.
+        let (start_x, start_y) = cell_to_tile(0, 0);
+        self.starting_position = Position {
+            x: start_x,
+            y: start_y,
+        };
+        let (stairs_x, stairs_y) = cell_to_tile(grid_width - 1, grid_height - 1);
+        let stairs_idx = self.map.xy_idx(stairs_x, stairs_y);
+        self.map.tiles[stairs_idx] = TileType::DownStairs;
+        self.take_snapshot();
+
+        // Fill each spawn area with monsters and items.
+        let spawn_areas = generate_voronoi_areas(&self.map, rng);
+        for area in spawn_areas.iter() {
+            spawner::spawn_region(rng, area, self.map.depth, &mut self.spawn_list);
+        }
+    }
+
+    /// Carve a perfect maze with a depth-first recursive backtracker.
+    fn carve_maze(&mut self, grid_width: i32, grid_height: i32, rng: &mut RandomNumberGenerator) {
+        let mut visited = vec![false; (grid_width * grid_height) as usize];
+        let mut backtrace: Vec<(i32, i32)> = vec![(0, 0)];
+        visited[0] = true;
+        self.carve_cell(0, 0);
+        let mut carved = 1;
+
+        while let Some(&(cell_x, cell_y)) = backtrace.last() {
+            let unvisited: Vec<(i32, i32)> = DIRECTIONS
+                .iter()
+                .filter(|(dx, dy)| {
+                    let (nx, ny) = (cell_x + dx, cell_y + dy);
+                    nx >= 0
+                        && nx < grid_width
+                        && ny >= 0
+                        && ny < grid_height
+                        && !visited[(ny * grid_width + nx) as usize]
+                })
+                .copied()
+                .collect();
+
+            if unvisited.is_empty() {
+                // Dead end, so backtrack to the last cell with unvisited neighbors.
+                backtrace.pop();
+                continue;
+            }
+
+            let (dx, dy) = unvisited[(rng.roll_dice(1, unvisited.len() as i32) - 1) as usize];
+            let (next_x, next_y) = (cell_x + dx, cell_y + dy);
+            self.knock_wall(cell_x, cell_y, dx, dy);
+            self.carve_cell(next_x, next_y);
+            visited[(next_y * grid_width + next_x) as usize] = true;
+            backtrace.push((next_x, next_y));
+
+            carved += 1;
+            if carved % CELLS_PER_SNAPSHOT == 0 {
+                self.take_snapshot();
+            }
+        }
+    }
+
+    /// Knock through random walls between neighboring cells to add loops.
+    fn knock_through_loops(
+        &mut self,
+        grid_width: i32,
+        grid_height: i32,
+        rng: &mut RandomNumberGenerator,
+    ) {
+        for _ in 0..self.extra_loops {
+            let cell_x = rng.roll_dice(1, grid_width) - 1;
+            let cell_y = rng.roll_dice(1, grid_height) - 1;
+            let (dx, dy) = DIRECTIONS[(rng.roll_dice(1, 4) - 1) as usize];
+            let (nx, ny) = (cell_x + dx, cell_y + dy);
+            if nx >= 0 && nx < grid_width && ny >= 0 && ny < grid_height {
+                self.knock_wall(cell_x, cell_y, dx, dy);
+            }
+        }
+    }
+
+    fn carve_cell(&mut self, cell_x: i32, cell_y: i32) {
+        let (x, y) = cell_to_tile(cell_x, cell_y);
+        let idx = self.map.xy_idx(x, y);
+        self.map.tiles[idx] = TileType::Floor;
+    }
+
+    /// Turn the wall tile between a cell and its neighbor in direction dx, dy into floor.
+    fn knock_wall(&mut self, cell_x: i32, cell_y: i32, dx: i32, dy: i32) {
+        let (x, y) = cell_to_tile(cell_x, cell_y);
+        let idx = self.map.xy_idx(x + dx, y + dy);
+        self.map.tiles[idx] = TileType::Floor;
+    }
+}
+
+/// Map tile coordinates for the middle of a maze cell.
+fn cell_to_tile(cell_x: i32, cell_y: i32) -> (i32, i32) {
+    (1 + cell_x * 2, 1 + cell_y * 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{MAPHEIGHT, MAPWIDTH};
+    use crate::map_builders::common::count_unreachable_floor_tiles;
+
+    /// Every cell is carved from the start, so nothing should be cut off.
+    fn assert_fully_reachable(mut builder: MazeBuilder, seed: u64) {
+        let mut rng = RandomNumberGenerator::seeded(seed);
+        builder.build_map(&mut rng);
+
+        let mut map = builder.get_map();
+        map.populate_blocked();
+        let start = builder.get_starting_position();
+        let start_idx = map.xy_idx(start.x, start.y);
+        assert_eq!(
+            count_unreachable_floor_tiles(&map, start_idx),
+            0,
+            "seed {} left floor unreachable",
+            seed
+        );
+    }
+
+    #[test]
+    fn perfect_maze_is_fully_reachable() {
+        for seed in 1..=8 {
+            assert_fully_reachable(MazeBuilder::perfect(1, MAPWIDTH, MAPHEIGHT), seed);
+        }
+    }
+
+    #[test]
+    fn labyrinth_is_fully_reachable() {
+        for seed in 1..=8 {
+            assert_fully_reachable(MazeBuilder::labyrinth(1, MAPWIDTH, MAPHEIGHT), seed);
+        }
+    }
+}
@@ -0,0 +1,358 @@
+//! Logic for building maps with different generation algorithms.
+
+mod bsp_dungeon;
+mod cellular_automata;
+mod common;
+mod dla;
+mod drunkard;
+mod maze;
+mod prefab_builder;
+mod simple_map;
+mod town;
+mod validation;
+mod waveform_collapse;
+
+pub use common::{is_standable, up_stairs_idx};
+pub use validation::{path_to_stairs_length, validate_map};
+
+use crate::{
+    Map, Position,
+    level_theme::LevelTheme,
+    map::{MAPHEIGHT, MAPWIDTH},
+    raws, spawner,
+};
+use bsp_dungeon::BspDungeonBuilder;
+use cellular_automata::CellularAutomataBuilder;
+use dla::DLABuilder;
+use drunkard::DrunkardsWalkBuilder;
+use maze::MazeBuilder;
+use prefab_builder::{PrefabBuilder, prefab_levels, prefab_sections};
+use rltk::RandomNumberGenerator;
+use simple_map::SimpleMapBuilder;
+use specs::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use town::TownBuilder;
+use waveform_collapse::WaveformCollapseBuilder;
+
+/// Interface for map generation algorithms.
+///
+/// A builder owns the map it generates, so spawning and the starting position stay consistent with the layout it built.
+pub trait MapBuilder {
+    /// Generate the map layout.
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator);
+    /// Get a copy of the built map.
+    fn get_map(&self) -> Map;
+    /// Get the position the player should start at on the built map.
+    fn get_starting_position(&self) -> Position;
+    /// Get the monsters and items to spawn on the built map, as (map tile index, spawn name).
+    fn get_spawn_list(&self) -> &Vec<(usize, String)>;
+    /// Get the snapshots of the map taken while it was built, oldest first.
+    fn get_snapshot_history(&self) -> &Vec<Map>;
+    /// Record a snapshot of the map as it is now, if snapshots are being recorded.
+    fn take_snapshot(&mut self);
+
+    /// Check if the level is laid out as rooms, so validation insists on enough of them.
+    fn is_room_based(&self) -> bool {
+        false
+    }
+
+    /// Spawn monsters and items onto the built map, rolling anything left to chance with rng, the generator the level was built with.
+    ///
+    /// Groups spread out onto the free tiles around them, so they're spawned last to keep clear of everything else.
+    fn spawn_entities(&mut self, ecs: &mut World, rng: &mut RandomNumberGenerator) {
+        let (groups, singles): (Vec<_>, Vec<_>) = self
+            .get_spawn_list()
+            .iter()
+            .partition(|entity| raws::raws().is_group(&entity.1));
+        for entity in singles.iter().chain(groups.iter()) {
+            spawner::spawn_entity(ecs, rng, &(&entity.0, &entity.1));
+        }
+    }
+}
+
+/// Whether builders record snapshots of the map as they go, for replaying generation step by step.
+///
+/// Off unless asked for, since copying the map at every step slows building down.
+static RECORD_SNAPSHOTS: AtomicBool = AtomicBool::new(false);
+
+/// Turn recording of generation snapshots on or off for every level built from now on.
+pub fn set_record_snapshots(enabled: bool) {
+    RECORD_SNAPSHOTS.store(enabled, Ordering::Relaxed);
+}
+
+/// Check if builders should record generation snapshots.
+pub fn is_recording_snapshots() -> bool {
+    RECORD_SNAPSHOTS.load(Ordering::Relaxed)
+}
+
+/// Depth of the town the player starts in, above the first dungeon level.
+pub const TOWN_DEPTH: i32 = 0;
+
+/// Every this many levels the player finds a labyrinth instead of a random level.
+const LABYRINTH_INTERVAL: i32 = 5;
+
+/// Depth of the hand-built keep level.
+const KEEP_DEPTH: i32 = 7;
+
+/// One in this many levels below the first is the Nyan Cat picture instead of a random level.
+const NYAN_CAT_CHANCE: i32 = 100;
+
+/// Width of a sprawling level, much bigger than the screen.
+const LARGE_MAP_WIDTH: i32 = 200;
+/// Height of a sprawling level, much bigger than the screen.
+const LARGE_MAP_HEIGHT: i32 = 120;
+
+/// Most times a level is built before the last attempt is kept, even if it fails validation.
+const MAX_BUILD_ATTEMPTS: i32 = 10;
+
+/// Build a level for the given depth, building it again whenever it fails validation.
+///
+/// Every attempt draws from the same generator, so a retry builds a different level but the result is still fixed by the seed.
+pub fn build_level(new_depth: i32, rng: &mut RandomNumberGenerator) -> Box<dyn MapBuilder> {
+    build_until_valid(new_depth, rng, random_builder)
+}
+
+/// Build levels from the builders select picks until one passes validation, or MAX_BUILD_ATTEMPTS have been built.
+fn build_until_valid(
+    new_depth: i32,
+    rng: &mut RandomNumberGenerator,
+    mut select: impl FnMut(i32, &mut RandomNumberGenerator) -> Box<dyn MapBuilder>,
+) -> Box<dyn MapBuilder> {
+    let mut attempt = 1;
+    loop {
+        let mut builder = select(new_depth, rng);
+        builder.build_map(rng);
+        let result = validation::validate_map(
+            &builder.get_map(),
+            &builder.get_starting_position(),
+            builder.is_room_based(),
+        );
+        match result {
+            Ok(()) => return builder,
+            Err(reason) if attempt < MAX_BUILD_ATTEMPTS => {
+                rltk::console::log(format!(
+                    "Rebuilding level {} after attempt {}: {}",
+                    new_depth, attempt, reason
+                ));
+                attempt += 1;
+            }
+            Err(reason) => {
+                rltk::console::log(format!(
+                    "Keeping level {} after {} attempts, though {}",
+                    new_depth, attempt, reason
+                ));
+                return builder;
+            }
+        }
+    }
+}
+
+/// Names of the builders that named_builder knows.
+pub const BUILDER_NAMES: [&str; 18] = [
+    "simple",
+    "bsp",
+    "cellular",
+    "open_area",
+    "open_halls",
+    "winding_passages",
+    "walk_inwards",
+    "walk_outwards",
+    "central_attractor",
+    "insectoid",
+    "crystal",
+    "totem",
+    "perfect_maze",
+    "labyrinth",
+    "keep",
+    "nyan_cat",
+    "wfc",
+    "town",
+];
+
+/// Select a map builder by name, for building a particular kind of level outside the game.
+///
+/// The keep, Nyan Cat, scrambled keep, and town are hand-made at a fixed size, so they ignore width and height.
+pub fn named_builder(
+    name: &str,
+    new_depth: i32,
+    width: i32,
+    height: i32,
+) -> Option<Box<dyn MapBuilder>> {
+    let builder: Box<dyn MapBuilder> = match name {
+        "simple" => Box::new(SimpleMapBuilder::new(new_depth, width, height)),
+        "bsp" => Box::new(BspDungeonBuilder::new(new_depth, width, height)),
+        "cellular" => Box::new(CellularAutomataBuilder::new(new_depth, width, height)),
+        "open_area" => Box::new(DrunkardsWalkBuilder::open_area(new_depth, width, height)),
+        "open_halls" => Box::new(DrunkardsWalkBuilder::open_halls(new_depth, width, height)),
+        "winding_passages" => Box::new(DrunkardsWalkBuilder::winding_passages(
+            new_depth, width, height,
+        )),
+        "walk_inwards" => Box::new(DLABuilder::walk_inwards(new_depth, width, height)),
+        "walk_outwards" => Box::new(DLABuilder::walk_outwards(new_depth, width, height)),
+        "central_attractor" => Box::new(DLABuilder::central_attractor(new_depth, width, height)),
+        "insectoid" => Box::new(DLABuilder::insectoid(new_depth, width, height)),
+        "crystal" => Box::new(DLABuilder::crystal(new_depth, width, height)),
+        "totem" => Box::new(DLABuilder::totem(new_depth, width, height)),
+        "perfect_maze" => Box::new(MazeBuilder::perfect(new_depth, width, height)),
+        "labyrinth" => Box::new(MazeBuilder::labyrinth(new_depth, width, height)),
+        "keep" => Box::new(PrefabBuilder::rex_level(new_depth, prefab_levels::KEEP)),
+        "nyan_cat" => Box::new(PrefabBuilder::rex_level(new_depth, prefab_levels::NYAN_CAT)),
+        "wfc" => Box::new(WaveformCollapseBuilder::derived_map(
+            new_depth,
+            Box::new(PrefabBuilder::rex_level(new_depth, prefab_levels::KEEP)),
+        )),
+        "town" => Box::new(TownBuilder::new(new_depth)),
+        _ => return None,
+    };
+    Some(builder)
+}
+
+/// Select a map builder for the given depth, shaped by the depth's theme.
+fn random_builder(new_depth: i32, rng: &mut RandomNumberGenerator) -> Box<dyn MapBuilder> {
+    if new_depth == TOWN_DEPTH {
+        return Box::new(TownBuilder::new(new_depth));
+    }
+
+    if new_depth == KEEP_DEPTH {
+        return Box::new(PrefabBuilder::rex_level(new_depth, prefab_levels::KEEP));
+    }
+
+    if new_depth % LABYRINTH_INTERVAL == 0 {
+        // Deeper labyrinths are perfect mazes, with no shortcuts.
+        if new_depth % (LABYRINTH_INTERVAL * 2) == 0 {
+            return Box::new(MazeBuilder::perfect(new_depth, MAPWIDTH, MAPHEIGHT));
+        }
+        return Box::new(MazeBuilder::labyrinth(new_depth, MAPWIDTH, MAPHEIGHT));
+    }
+
+    if new_depth > 1 && rng.roll_dice(1, NYAN_CAT_CHANCE) == 1 {
+        return Box::new(PrefabBuilder::rex_level(new_depth, prefab_levels::NYAN_CAT));
+    }
+
+    // Past the first couple of levels, some sprawl well beyond the edges of the screen.
+    let (width, height) = if new_depth >= 3 && rng.roll_dice(1, 4) == 1 {
+        (LARGE_MAP_WIDTH, LARGE_MAP_HEIGHT)
+    } else {
+        (MAPWIDTH, MAPHEIGHT)
+    };
+
+    let theme = LevelTheme::for_depth(new_depth);
+    let builder = themed_builder(theme, new_depth, width, height, rng);
+
+    // Sometimes scramble the level into a new one with the same local structure.
+    // Solving takes far too long on sprawling levels, so only standard-size ones are scrambled.
+    let builder: Box<dyn MapBuilder> = if width == MAPWIDTH && rng.roll_dice(1, 6) == 1 {
+        Box::new(WaveformCollapseBuilder::derived_map(new_depth, builder))
+    } else {
+        builder
+    };
+
+    // Often overlay a section that suits the theme.
+    let themed_section = match theme {
+        LevelTheme::Dungeon => None,
+        LevelTheme::GoblinWarren => Some(prefab_sections::GOBLIN_DEN),
+        LevelTheme::FloodedCrypt => Some(prefab_sections::FLOODED_CAVERN),
+        LevelTheme::OrcBarracks => Some(prefab_sections::ORC_OUTPOST),
+        LevelTheme::MoltenDepths => Some(if rng.roll_dice(1, 2) == 1 {
+            prefab_sections::LAVA_FISSURE
+        } else {
+            prefab_sections::CHASM_BRIDGE
+        }),
+    };
+    if let Some(section) = themed_section
+        && rng.roll_dice(1, 2) == 1
+    {
+        return Box::new(PrefabBuilder::sectional(new_depth, section, builder));
+    }
+
+    // Occasionally dress the level up with prefab vaults or an overlaid section.
+    match rng.roll_dice(1, 11) {
+        1 => Box::new(PrefabBuilder::vaults(new_depth, builder)),
+        2 => Box::new(PrefabBuilder::sectional(
+            new_depth,
+            prefab_sections::ORC_OUTPOST,
+            builder,
+        )),
+        3 => Box::new(PrefabBuilder::sectional(
+            new_depth,
+            prefab_sections::GOBLIN_DEN,
+            builder,
+        )),
+        4 => Box::new(PrefabBuilder::sectional(
+            new_depth,
+            prefab_sections::RUINED_SHRINE,
+            builder,
+        )),
+        5 => Box::new(PrefabBuilder::sectional(
+            new_depth,
+            prefab_sections::FLOODED_CAVERN,
+            builder,
+        )),
+        6 => Box::new(PrefabBuilder::sectional(
+            new_depth,
+            prefab_sections::LAVA_FISSURE,
+            builder,
+        )),
+        7 => Box::new(PrefabBuilder::sectional(
+            new_depth,
+            prefab_sections::CHASM_BRIDGE,
+            builder,
+        )),
+        _ => builder,
+    }
+}
+
+/// Select a layout builder suited to the level's theme.
+fn themed_builder(
+    theme: LevelTheme,
+    new_depth: i32,
+    width: i32,
+    height: i32,
+    rng: &mut RandomNumberGenerator,
+) -> Box<dyn MapBuilder> {
+    match theme {
+        LevelTheme::Dungeon => {
+            // Organic, tunnel-heavy levels only show up once the player is a few levels down.
+            let choices = if new_depth < 3 { 3 } else { 13 };
+            match rng.roll_dice(1, choices) {
+                1 => Box::new(SimpleMapBuilder::new(new_depth, width, height)),
+                2 => Box::new(BspDungeonBuilder::new(new_depth, width, height)),
+                3 => Box::new(CellularAutomataBuilder::new(new_depth, width, height)),
+                4 => Box::new(DrunkardsWalkBuilder::open_area(new_depth, width, height)),
+                5 => Box::new(DrunkardsWalkBuilder::open_halls(new_depth, width, height)),
+                6 => Box::new(DrunkardsWalkBuilder::winding_passages(
+                    new_depth, width, height,
+                )),
+                7 => Box::new(DLABuilder::walk_inwards(new_depth, width, height)),
+                8 => Box::new(DLABuilder::walk_outwards(new_depth, width, height)),
+                9 => Box::new(DLABuilder::central_attractor(new_depth, width, height)),
+                10 => Box::new(DLABuilder::insectoid(new_depth, width, height)),
+                11 => Box::new(DLABuilder::crystal(new_depth, width, height)),
+                12 => Box::new(DLABuilder::totem(new_depth, width, height)),
+                _ => Box::new(WaveformCollapseBuilder::derived_map(
+                    new_depth,
+                    Box::new(PrefabBuilder::rex_level(new_depth, prefab_levels::KEEP)),
+                )),
+            }
+        }
+        // Warrens are dug, not built: narrow, twisting tunnels.
+        LevelTheme::GoblinWarren => match rng.roll_dice(1, 3) {
+            1 => Box::new(DrunkardsWalkBuilder::winding_passages(
+                new_depth, width, height,
+            )),
+            2 => Box::new(DLABuilder::walk_outwards(new_depth, width, height)),
+            _ => Box::new(CellularAutomataBuilder::new(new_depth, width, height)),
+        },
+        // Crypts and barracks are built by hand, out of rooms and corridors.
+        LevelTheme::FloodedCrypt | LevelTheme::OrcBarracks => match rng.roll_dice(1, 2) {
+            1 => Box::new(SimpleMapBuilder::new(new_depth, width, height)),
+            _ => Box::new(BspDungeonBuilder::new(new_depth, width, height)),
+        },
+        // Wide caves, as if melted out of the rock.
+        LevelTheme::MoltenDepths => match rng.roll_dice(1, 3) {
+            1 => Box::new(DrunkardsWalkBuilder::open_area(new_depth, width, height)),
+            2 => Box::new(DLABuilder::central_attractor(new_depth, width, height)),
+            _ => Box::new(CellularAutomataBuilder::new(new_depth, width, height)),
+        },
+    }
+}
@@ -0,0 +1,464 @@
+//! Map builder for hand-made prefab levels, sections, and vaults.
+//!
+//! Layouts are drawn either in REX Paint .xp files or as plain-text ASCII, using the same glyphs:
+//! `#` wall, ` ` or `.` floor, `>` stairs down, `@` player start, and spawn markers such as `g` goblin, `o` orc, `!` health potion, `/` dagger, and `(` shield.
+//! Terrain uses `~` shallow water, `W` deep water, `L` lava, `;` rubble, `=` bridge, and `_` chasm.
+//! REX Paint levels can also be drawn as pixel art in solid blocks (`█`), read by color: black blocks are wall, and any other color is floor.
+
+pub mod prefab_levels;
+pub mod prefab_rooms;
+pub mod prefab_sections;
+
+use super::{
+    MapBuilder,
+    common::{
+        count_unreachable_floor_tiles, generate_voronoi_areas, populate_blocked_for_generation,
+        record_snapshot, remove_unreachable_areas_returning_most_distant,
+    },
+};
+use crate::{
+    Map, Position, TileType,
+    map::{MAPHEIGHT, MAPWIDTH},
+    spawner,
+};
+use prefab_levels::RexLevel;
+use prefab_rooms::PrefabRoom;
+use prefab_sections::{HorizontalPlacement, PrefabSection, VerticalPlacement};
+use rltk::{RandomNumberGenerator, rex::XpFile};
+use std::collections::HashSet;
+
+/// Code page 437 solid block, which REX Paint levels drawn as pixel art are made of.
+const SOLID_BLOCK: u32 = 219;
+
+/// How a prefab is applied to the level.
+#[derive(PartialEq, Copy, Clone)]
+pub enum PrefabMode {
+    /// Load a whole level from a REX Paint file.
+    RexLevel { level: RexLevel },
+    /// Overlay a section onto a level from another builder.
+    Sectional { section: PrefabSection },
+    /// Stamp room-sized vaults into open floor of a level from another builder.
+    RoomVaults,
+}
+
+/// Builds levels from prefab layouts, either on their own or on top of another builder's level.
+pub struct PrefabBuilder {
+    map: Map,
+    starting_position: Position,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Map>,
+    mode: PrefabMode,
+    previous_builder: Option<Box<dyn MapBuilder>>,
+}
+
+impl MapBuilder for PrefabBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        &self.spawn_list
+    }
+
+    fn get_snapshot_history(&self) -> &Vec<Map> {
+        &self.history
+    }
+
+    fn take_snapshot(&mut self) {
+        record_snapshot(&mut self.history, &self.map);
+    }
+
+    fn is_room_based(&self) -> bool {
+        // Prefabs keep the rooms of the level they're built on.
+        self.previous_builder
+            .as_ref()
+            .is_some_and(|builder| builder.is_room_based())
+    }
+}
+
+impl PrefabBuilder {
+    pub fn new(
+        new_depth: i32,
+        mode: PrefabMode,
+        previous_builder: Option<Box<dyn MapBuilder>>,
+    ) -> PrefabBuilder {
+        PrefabBuilder {
+            // Levels built on another builder's take over its map, and its size, when they're built.
+            map: Map::new(new_depth, MAPWIDTH, MAPHEIGHT),
+            starting_position: Position { x: 0, y: 0 },
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+            mode,
+            previous_builder,
+        }
+    }
+
+    /// Whole level loaded from a REX Paint file.
+    pub fn rex_level(new_depth: i32, level: RexLevel) -> PrefabBuilder {
+        PrefabBuilder::new(new_depth, PrefabMode::RexLevel { level }, None)
+    }
+
+    /// Section overlaid onto a level built by previous_builder.
+    pub fn sectional(
+        new_depth: i32,
+        section: PrefabSection,
+        previous_builder: Box<dyn MapBuilder>,
+    ) -> PrefabBuilder {
+        PrefabBuilder::new(
+            new_depth,
+            PrefabMode::Sectional { section },
+            Some(previous_builder),
+        )
+    }
+
+    /// Vaults stamped into a level built by previous_builder.
+    pub fn vaults(new_depth: i32, previous_builder: Box<dyn MapBuilder>) -> PrefabBuilder {
+        PrefabBuilder::new(new_depth, PrefabMode::RoomVaults, Some(previous_builder))
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        match self.mode {
+            PrefabMode::RexLevel { level } => {
+                self.load_rex_map(&level);
+                self.take_snapshot();
+                // Levels drawn without stairs get a start, stairs, and spawns placed, as caves do.
+                if !self.map.tiles.contains(&TileType::DownStairs) {
+                    self.place_start_and_stairs();
+                    self.take_snapshot();
+                    for area in generate_voronoi_areas(&self.map, rng).iter() {
+                        spawner::spawn_region(rng, area, self.map.depth, &mut self.spawn_list);
+                    }
+                }
+            }
+            PrefabMode::Sectional { section } => self.apply_sectional(&section, rng),
+            PrefabMode::RoomVaults => self.apply_room_vaults(rng),
+        }
+    }
+
+    /// Apply a single prefab glyph to the map tile at idx, queuing any spawn it marks.
+    fn char_to_map(&mut self, ch: char, idx: usize) {
+        let spawn = match ch {
+            ' ' | '.' => {
+                self.map.tiles[idx] = TileType::Floor;
+                None
+            }
+            '#' => {
+                self.map.tiles[idx] = TileType::Wall;
+                None
+            }
+            '>' => {
+                self.map.tiles[idx] = TileType::DownStairs;
+                None
+            }
+            '~' => {
+                self.map.tiles[idx] = TileType::ShallowWater;
+                None
+            }
+            'W' => {
+                self.map.tiles[idx] = TileType::DeepWater;
+                None
+            }
+            'L' => {
+                self.map.tiles[idx] = TileType::Lava;
+                None
+            }
+            ';' => {
+                self.map.tiles[idx] = TileType::Rubble;
+                None
+            }
+            '=' => {
+                self.map.tiles[idx] = TileType::Bridge;
+                None
+            }
+            '_' => {
+                self.map.tiles[idx] = TileType::Chasm;
+                None
+            }
+            '@' => {
+                self.map.tiles[idx] = TileType::Floor;
+                self.starting_position = Position {
+                    x: idx as i32 % self.map.width,
+                    y: idx as i32 / self.map.width,
+                };
+                None
+            }
+            'g' => Some("Goblin"),
+            'o' => Some("Orc"),
+            '!' => Some("Health Potion"),
+            '/' => Some("Dagger"),
+            '(' => Some("Shield"),
+            _ => {
+                rltk::console::log(format!("Unknown glyph loading prefab: '{}'", ch));
+                None
+            }
+        };
+
+        if let Some(name) = spawn {
+            self.map.tiles[idx] = TileType::Floor;
+            self.spawn_list.push((idx, name.to_string()));
+        }
+    }
+
+    /// Load a whole level from a REX Paint file. Later layers are drawn over earlier ones, skipping blank cells.
+    ///
+    /// Layouts smaller than the map are centered on it, leaving wall all around.
+    fn load_rex_map(&mut self, level: &RexLevel) {
+        let xp_file = XpFile::read(&mut &level.template[..]).expect("Unable to read prefab level");
+
+        for (i, layer) in xp_file.layers.iter().enumerate() {
+            let offset_x = i32::max(0, (self.map.width - layer.width as i32) / 2);
+            let offset_y = i32::max(0, (self.map.height - layer.height as i32) / 2);
+            for y in 0..layer.height {
+                for x in 0..layer.width {
+                    let cell = layer.get(x, y).unwrap();
+                    // Only the bottom layer may paint floor with blank cells.
+                    if i > 0 && (cell.ch == 0 || cell.ch == 32) {
+                        continue;
+                    }
+                    let (map_x, map_y) = (x as i32 + offset_x, y as i32 + offset_y);
+                    if map_x < self.map.width && map_y < self.map.height {
+                        let idx = self.map.xy_idx(map_x, map_y);
+                        if cell.ch == SOLID_BLOCK {
+                            let black = cell.fg.r == 0 && cell.fg.g == 0 && cell.fg.b == 0;
+                            self.map.tiles[idx] = if black {
+                                TileType::Wall
+                            } else {
+                                TileType::Floor
+                            };
+                            continue;
+                        }
+                        let ch = if cell.ch == 0 {
+                            ' '
+                        } else {
+                            rltk::to_char(cell.ch as u8)
+                        };
+                        self.char_to_map(ch, idx);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Start the player at the floor tile nearest the middle of the map, wall off what they can't reach, and put the stairs down as far away as possible.
+    fn place_start_and_stairs(&mut self) {
+        let center = rltk::Point::new(self.map.width / 2, self.map.height / 2);
+        let start_idx = self
+            .map
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| **tile == TileType::Floor)
+            .map(|(idx, _)| idx)
+            .min_by_key(|idx| {
+                let x = *idx as i32 % self.map.width;
+                let y = *idx as i32 / self.map.width;
+                i32::abs(x - center.x) + i32::abs(y - center.y)
+            });
+        // With no floor at all, leave the level for validation to reject.
+        let Some(start_idx) = start_idx else {
+            return;
+        };
+        self.starting_position = Position {
+            x: start_idx as i32 % self.map.width,
+            y: start_idx as i32 / self.map.width,
+        };
+
+        let exit_tile = remove_unreachable_areas_returning_most_distant(&mut self.map, start_idx);
+        self.map.tiles[exit_tile] = TileType::DownStairs;
+    }
+
+    /// Run the previous builder and take over its map, starting position, and spawns.
+    fn build_previous(&mut self, rng: &mut RandomNumberGenerator) {
+        let previous_builder = self
+            .previous_builder
+            .as_mut()
+            .expect("Prefab needs a previous builder to build on");
+        previous_builder.build_map(rng);
+        self.map = previous_builder.get_map();
+        self.starting_position = previous_builder.get_starting_position();
+        self.spawn_list = previous_builder.get_spawn_list().clone();
+        self.history = previous_builder.get_snapshot_history().clone();
+    }
+
+    /// Stamp an ASCII template onto the map with its top-left corner at x, y, replacing any spawns underneath it.
+    fn stamp(&mut self, template: &str, width: usize, height: usize, x: i32, y: i32) {
+        let map_width = self.map.width;
+        let (x2, y2) = (x + width as i32, y + height as i32);
+        self.spawn_list.retain(|(idx, _)| {
+            let spawn_x = *idx as i32 % map_width;
+            let spawn_y = *idx as i32 / map_width;
+            !(spawn_x >= x && spawn_x < x2 && spawn_y >= y && spawn_y < y2)
+        });
+
+        let glyphs = read_ascii_to_vec(template, width, height);
+        for ty in 0..height {
+            for tx in 0..width {
+                let idx = self.map.xy_idx(x + tx as i32, y + ty as i32);
+                self.char_to_map(glyphs[ty * width + tx], idx);
+            }
+        }
+    }
+
+    /// Stamp a template, keeping it only if the player can still reach the stairs and everything they could reach before.
+    ///
+    /// Returns false, leaving the map and spawns untouched, if the stamp would break connectivity.
+    fn try_stamp(&mut self, template: &str, width: usize, height: usize, x: i32, y: i32) -> bool {
+        let start = self.starting_position.clone();
+        if start.x >= x && start.x < x + width as i32 && start.y >= y && start.y < y + height as i32
+        {
+            return false;
+        }
+
+        let start_idx = self.map.xy_idx(start.x, start.y);
+        populate_blocked_for_generation(&mut self.map);
+        let unreachable_before = count_unreachable_floor_tiles(&self.map, start_idx);
+        let tiles_before = self.map.tiles.clone();
+        let spawns_before = self.spawn_list.clone();
+
+        self.stamp(template, width, height, x, y);
+
+        populate_blocked_for_generation(&mut self.map);
+        let has_stairs = self.map.tiles.contains(&TileType::DownStairs);
+        let unreachable_after = count_unreachable_floor_tiles(&self.map, start_idx);
+        if !has_stairs || unreachable_after > unreachable_before {
+            rltk::console::log("Rejected prefab stamp that would break map connectivity");
+            self.map.tiles = tiles_before;
+            self.spawn_list = spawns_before;
+            self.map.populate_blocked();
+            return false;
+        }
+        self.map.populate_blocked();
+        self.take_snapshot();
+
+        true
+    }
+
+    /// Overlay a section onto the previous builder's level at the section's placement.
+    fn apply_sectional(&mut self, section: &PrefabSection, rng: &mut RandomNumberGenerator) {
+        self.build_previous(rng);
+
+        // Keep the section inside the map's outer wall.
+        let chunk_x = match section.placement.0 {
+            HorizontalPlacement::Left => 1,
+            HorizontalPlacement::Center => (self.map.width / 2) - (section.width as i32 / 2),
+            HorizontalPlacement::Right => (self.map.width - 1) - section.width as i32,
+        };
+        let chunk_y = match section.placement.1 {
+            VerticalPlacement::Top => 1,
+            VerticalPlacement::Center => (self.map.height / 2) - (section.height as i32 / 2),
+            VerticalPlacement::Bottom => (self.map.height - 1) - section.height as i32,
+        };
+
+        self.try_stamp(
+            section.template,
+            section.width,
+            section.height,
+            chunk_x,
+            chunk_y,
+        );
+    }
+
+    /// Stamp a few depth-appropriate vaults into open floor of the previous builder's level.
+    fn apply_room_vaults(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build_previous(rng);
+
+        let master_vault_list = [
+            prefab_rooms::GUARDED_POTION,
+            prefab_rooms::ARMORY,
+            prefab_rooms::ORC_GUARD_POST,
+        ];
+        let mut possible_vaults: Vec<&PrefabRoom> = master_vault_list
+            .iter()
+            .filter(|v| self.map.depth >= v.first_depth && self.map.depth <= v.last_depth)
+            .collect();
+        if possible_vaults.is_empty() {
+            return;
+        }
+
+        let n_vaults = i32::min(rng.roll_dice(1, 3), possible_vaults.len() as i32);
+        let mut used_tiles: HashSet<usize> = HashSet::new();
+        for _ in 0..n_vaults {
+            // Each vault shows up at most once per level.
+            let vault_index = (rng.roll_dice(1, possible_vaults.len() as i32) - 1) as usize;
+            let vault = possible_vaults.remove(vault_index);
+
+            // Find every spot where the whole vault would sit on unused, open floor.
+            let mut vault_positions: Vec<Position> = Vec::new();
+            for y in 1..self.map.height - vault.height as i32 - 1 {
+                for x in 1..self.map.width - vault.width as i32 - 1 {
+                    let mut possible = true;
+                    'vault: for ty in 0..vault.height as i32 {
+                        for tx in 0..vault.width as i32 {
+                            let idx = self.map.xy_idx(x + tx, y + ty);
+                            if self.map.tiles[idx] != TileType::Floor || used_tiles.contains(&idx) {
+                                possible = false;
+                                break 'vault;
+                            }
+                        }
+                    }
+                    if possible {
+                        vault_positions.push(Position { x, y });
+                    }
+                }
+            }
+            if vault_positions.is_empty() {
+                continue;
+            }
+
+            let pos_index = (rng.roll_dice(1, vault_positions.len() as i32) - 1) as usize;
+            let pos = &vault_positions[pos_index];
+            if self.try_stamp(vault.template, vault.width, vault.height, pos.x, pos.y) {
+                for ty in 0..vault.height as i32 {
+                    for tx in 0..vault.width as i32 {
+                        used_tiles.insert(self.map.xy_idx(pos.x + tx, pos.y + ty));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Flatten an ASCII template into a width * height vector of glyphs, padding short lines with floor.
+///
+/// Templates start with a newline so they line up nicely in source, which is skipped.
+fn read_ascii_to_vec(template: &str, width: usize, height: usize) -> Vec<char> {
+    let mut glyphs = vec![' '; width * height];
+    for (y, line) in template
+        .trim_start_matches('\n')
+        .lines()
+        .take(height)
+        .enumerate()
+    {
+        for (x, ch) in line.chars().take(width).enumerate() {
+            glyphs[y * width + x] = ch;
+        }
+    }
+    glyphs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_builders::validate_map;
+
+    #[test]
+    fn pixel_art_level_gets_a_start_and_stairs() {
+        let mut builder = PrefabBuilder::rex_level(1, prefab_levels::NYAN_CAT);
+        builder.build_map(&mut RandomNumberGenerator::seeded(1));
+
+        let map = builder.get_map();
+        assert!(map.tiles.contains(&TileType::DownStairs));
+        assert_eq!(
+            validate_map(&map, &builder.get_starting_position(), false),
+            Ok(())
+        );
+    }
+}
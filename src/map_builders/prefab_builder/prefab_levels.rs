@@ -0,0 +1,18 @@
+//! Whole-level prefab layouts.
+
+/// A whole-level layout drawn in a REX Paint .xp file.
+#[derive(PartialEq, Copy, Clone)]
+pub struct RexLevel {
+    /// Raw bytes of the gzipped .xp file.
+    pub template: &'static [u8],
+}
+
+/// Fortified keep with a gatehouse to the west and the stairs down beyond the throne room to the east.
+pub const KEEP: RexLevel = RexLevel {
+    template: include_bytes!("../../../resources/prefab_keep.xp"),
+};
+
+/// Nyan Cat pixel art, with the cat's black outline as walls and the night sky around it as open floor.
+pub const NYAN_CAT: RexLevel = RexLevel {
+    template: include_bytes!("../../../resources/nyan.xp"),
+};
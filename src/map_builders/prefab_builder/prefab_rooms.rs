@@ -0,0 +1,65 @@
+//! Room-sized prefab vaults stamped into open floor of a generated level.
+
+/// An ASCII layout for a vault, available between first_depth and last_depth inclusive.
+///
+/// Vault edges should be floor so stamping one into open floor never cuts the surrounding area off.
+#[derive(PartialEq, Copy, Clone)]
+pub struct PrefabRoom {
+    pub template: &'static str,
+    pub width: usize,
+    pub height: usize,
+    pub first_depth: i32,
+    pub last_depth: i32,
+}
+
+/// Potion behind a pair of goblin guards.
+pub const GUARDED_POTION: PrefabRoom = PrefabRoom {
+    template: GUARDED_POTION_TEMPLATE,
+    width: 5,
+    height: 5,
+    first_depth: 0,
+    last_depth: 100,
+};
+
+const GUARDED_POTION_TEMPLATE: &str = "
+     
+ #g# 
+ #!# 
+ ### 
+     
+";
+
+/// Small armory with a weapon and a shield.
+pub const ARMORY: PrefabRoom = PrefabRoom {
+    template: ARMORY_TEMPLATE,
+    width: 7,
+    height: 5,
+    first_depth: 2,
+    last_depth: 100,
+};
+
+const ARMORY_TEMPLATE: &str = "
+       
+ ## ## 
+ #/ (# 
+ ##### 
+       
+";
+
+/// Orc guard post around a cache of potions.
+pub const ORC_GUARD_POST: PrefabRoom = PrefabRoom {
+    template: ORC_GUARD_POST_TEMPLATE,
+    width: 7,
+    height: 6,
+    first_depth: 3,
+    last_depth: 100,
+};
+
+const ORC_GUARD_POST_TEMPLATE: &str = "
+       
+ #o o# 
+ #!!!# 
+ #   # 
+ ## ## 
+       
+";
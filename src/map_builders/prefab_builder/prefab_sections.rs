@@ -0,0 +1,140 @@
+//! Prefab sections overlaid onto part of an already generated level.
+
+/// Where a section is placed across the width of the map.
+#[derive(PartialEq, Copy, Clone)]
+pub enum HorizontalPlacement {
+    Left,
+    Center,
+    Right,
+}
+
+/// Where a section is placed down the height of the map.
+#[derive(PartialEq, Copy, Clone)]
+pub enum VerticalPlacement {
+    Top,
+    Center,
+    Bottom,
+}
+
+/// An ASCII layout stamped over part of a generated level.
+#[derive(PartialEq, Copy, Clone)]
+pub struct PrefabSection {
+    pub template: &'static str,
+    pub width: usize,
+    pub height: usize,
+    pub placement: (HorizontalPlacement, VerticalPlacement),
+}
+
+/// Walled orc outpost against the east edge of the map, open to the west.
+pub const ORC_OUTPOST: PrefabSection = PrefabSection {
+    template: ORC_OUTPOST_TEMPLATE,
+    width: 15,
+    height: 11,
+    placement: (HorizontalPlacement::Right, VerticalPlacement::Center),
+};
+
+const ORC_OUTPOST_TEMPLATE: &str = "
+###############
+              #
+   o       o  #
+     ## ##    #
+     #! !#    #
+     #   #    #
+     #!/!#    #
+     #####    #
+   o       o  #
+              #
+###############
+";
+
+/// Goblin den tucked against the north edge of the map, open to the south.
+pub const GOBLIN_DEN: PrefabSection = PrefabSection {
+    template: GOBLIN_DEN_TEMPLATE,
+    width: 13,
+    height: 7,
+    placement: (HorizontalPlacement::Center, VerticalPlacement::Top),
+};
+
+const GOBLIN_DEN_TEMPLATE: &str = "
+#############
+#  g  !  g  #
+# ### # ### #
+#  g     g  #
+###  ###  ###
+             
+             
+";
+
+/// Ruined shrine in the south-west corner of the map, open to the north and east.
+pub const RUINED_SHRINE: PrefabSection = PrefabSection {
+    template: RUINED_SHRINE_TEMPLATE,
+    width: 12,
+    height: 8,
+    placement: (HorizontalPlacement::Left, VerticalPlacement::Bottom),
+};
+
+const RUINED_SHRINE_TEMPLATE: &str = "
+            
+ # # # #    
+            
+ #  !   #   
+     g      
+ #  (   #   
+ # # # #    
+            
+";
+
+/// Flooded cavern in the middle of the map: a deep pool ringed by shallows.
+pub const FLOODED_CAVERN: PrefabSection = PrefabSection {
+    template: FLOODED_CAVERN_TEMPLATE,
+    width: 15,
+    height: 8,
+    placement: (HorizontalPlacement::Center, VerticalPlacement::Center),
+};
+
+const FLOODED_CAVERN_TEMPLATE: &str = "
+               
+   ~~~~~~~~~   
+  ~~WWWWWWW~~  
+ ~~WWWWWWWWW~~ 
+ ~~WWWWWWWWW~~ 
+  ~~WWWWWWW~~  
+   ~~~~~~~~~   
+               
+";
+
+/// Lava fissure in the north-west corner of the map, strewn with rubble.
+pub const LAVA_FISSURE: PrefabSection = PrefabSection {
+    template: LAVA_FISSURE_TEMPLATE,
+    width: 13,
+    height: 7,
+    placement: (HorizontalPlacement::Left, VerticalPlacement::Top),
+};
+
+const LAVA_FISSURE_TEMPLATE: &str = "
+             
+ ;;  LLL  ;; 
+  ; LLLLL ;  
+   LLLLLLL   
+  ; LLLLL ;  
+ ;;  LLL  ;; 
+             
+";
+
+/// Chasm along the south edge of the map, crossed by a bridge.
+pub const CHASM_BRIDGE: PrefabSection = PrefabSection {
+    template: CHASM_BRIDGE_TEMPLATE,
+    width: 14,
+    height: 7,
+    placement: (HorizontalPlacement::Center, VerticalPlacement::Bottom),
+};
+
+const CHASM_BRIDGE_TEMPLATE: &str = "
+              
+ ____________ 
+ ____________ 
+ ============ 
+ ____________ 
+ ____________ 
+              
+";
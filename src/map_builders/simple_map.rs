@@ -0,0 +1,141 @@
+//! Map builder for random rooms connected by corridors.
+
+use super::{
+    MapBuilder,
+    common::{
+        apply_horizontal_tunnel, apply_room_to_map, apply_vertical_tunnel, place_doors,
+        record_snapshot,
+    },
+};
+use crate::{
+    Map, Position, TileType,
+    map::{MAPHEIGHT, MAPWIDTH},
+    rect::Rect,
+    spawner,
+};
+use rltk::RandomNumberGenerator;
+
+/// Builds maps of randomly placed rectangular rooms joined by L-shaped tunnels.
+pub struct SimpleMapBuilder {
+    map: Map,
+    starting_position: Position,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Map>,
+}
+
+impl MapBuilder for SimpleMapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.rooms_and_corridors(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        &self.spawn_list
+    }
+
+    fn get_snapshot_history(&self) -> &Vec<Map> {
+        &self.history
+    }
+
+    fn take_snapshot(&mut self) {
+        record_snapshot(&mut self.history, &self.map);
+    }
+
+    fn is_room_based(&self) -> bool {
+        true
+    }
+}
+
+impl SimpleMapBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> SimpleMapBuilder {
+        SimpleMapBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    /// Construct a map with random rooms and corridors.
+    ///
+    /// Sets the maximum number of rooms and the minimum and maximum room sizes.
+    fn rooms_and_corridors(&mut self, rng: &mut RandomNumberGenerator) {
+        // Maximum number of rooms on a standard-size map.
+        const MAX_ROOMS: i32 = 30;
+        const MIN_SIZE: i32 = 6;
+        const MAX_SIZE: i32 = 10;
+
+        // Bigger maps get proportionally more rooms.
+        let max_rooms = MAX_ROOMS * (self.map.width * self.map.height) / (MAPWIDTH * MAPHEIGHT);
+        for _ in 0..max_rooms {
+            // Generate new rng room.
+            let w = rng.range(MIN_SIZE, MAX_SIZE);
+            let h = rng.range(MIN_SIZE, MAX_SIZE);
+            let x = rng.roll_dice(1, self.map.width - w - 1) - 1;
+            let y = rng.roll_dice(1, self.map.height - h - 1) - 1;
+            let new_room = Rect::new(x, y, w, h);
+
+            // Use new rng room iff doesn't intersect with existing rooms.
+            let mut ok = true;
+            for other_room in self.map.rooms.iter() {
+                if new_room.intersect(other_room) {
+                    ok = false
+                }
+            }
+            if ok {
+                apply_room_to_map(&mut self.map, &new_room);
+
+                if !self.map.rooms.is_empty() {
+                    // Connect new room with last room.
+                    let (new_x, new_y) = new_room.center();
+                    let (prev_x, prev_y) = self.map.rooms[self.map.rooms.len() - 1].center();
+
+                    // RNG draw to see if start with horizontal or vertical tunnel first.
+                    if rng.range(0, 2) == 1 {
+                        apply_horizontal_tunnel(&mut self.map, prev_x, new_x, prev_y);
+                        apply_vertical_tunnel(&mut self.map, prev_y, new_y, new_x);
+                    } else {
+                        apply_vertical_tunnel(&mut self.map, prev_y, new_y, prev_x);
+                        apply_horizontal_tunnel(&mut self.map, prev_x, new_x, new_y);
+                    }
+                }
+
+                self.map.rooms.push(new_room);
+                self.take_snapshot();
+            }
+        }
+
+        // With no rooms there's nowhere for the stairs or the player, so leave the level for validation to reject.
+        if self.map.rooms.is_empty() {
+            return;
+        }
+
+        // Put doors where corridors run into rooms.
+        place_doors(&mut self.map, rng);
+
+        // Put stairs down in last-generated room.
+        let stairs_position = self.map.rooms[self.map.rooms.len() - 1].center();
+        let stairs_idx = self.map.xy_idx(stairs_position.0, stairs_position.1);
+        self.map.tiles[stairs_idx] = TileType::DownStairs;
+        self.take_snapshot();
+
+        // Start the player in the first room.
+        let (start_x, start_y) = self.map.rooms[0].center();
+        self.starting_position = Position {
+            x: start_x,
+            y: start_y,
+        };
+
+        // Fill every room but the player's with monsters and items.
+        for room in self.map.rooms.iter().skip(1) {
+            spawner::spawn_room(&self.map, rng, room, self.map.depth, &mut self.spawn_list);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Map builder for the town the player sets out from, above the first dungeon level.
+
+use super::{
+    MapBuilder,
+    common::{apply_room_to_map, record_snapshot},
+};
+use crate::{
+    Map, Position, TileType,
+    map::{MAPHEIGHT, MAPWIDTH},
+    rect::Rect,
+};
+use rltk::RandomNumberGenerator;
+
+/// Shopkeepers in town, one to a building while buildings last.
+const VENDORS: [&str; 4] = ["Blacksmith", "Armorer", "Alchemist", "Scribe"];
+
+/// Columns kept clear of buildings at the east end of the road, around the dungeon entrance.
+const ENTRANCE_CLEARING: i32 = 8;
+
+/// Builds a town of buildings strung along a main road, which runs west to east from the player to the dungeon entrance.
+///
+/// Buildings line both sides of the road, each with a door and a path out to it. Vendors keep shop in some, and townsfolk wander the streets.
+pub struct TownBuilder {
+    map: Map,
+    starting_position: Position,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Map>,
+}
+
+impl MapBuilder for TownBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        &self.spawn_list
+    }
+
+    fn get_snapshot_history(&self) -> &Vec<Map> {
+        &self.history
+    }
+
+    fn take_snapshot(&mut self) {
+        record_snapshot(&mut self.history, &self.map);
+    }
+}
+
+impl TownBuilder {
+    pub fn new(new_depth: i32) -> TownBuilder {
+        TownBuilder {
+            map: Map::new(new_depth, MAPWIDTH, MAPHEIGHT),
+            starting_position: Position { x: 0, y: 0 },
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        // Open ground inside the town walls.
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                let idx = self.map.xy_idx(x, y);
+                self.map.tiles[idx] = TileType::Grass;
+            }
+        }
+
+        // Main road, three tiles wide, across the middle of town.
+        let road_y = self.map.height / 2;
+        for y in road_y - 1..=road_y + 1 {
+            for x in 1..self.map.width - 1 {
+                let idx = self.map.xy_idx(x, y);
+                self.map.tiles[idx] = TileType::Road;
+            }
+        }
+
+        self.take_snapshot();
+
+        self.build_street(road_y - 2, true, rng);
+        self.build_street(road_y + 2, false, rng);
+
+        // The dungeon entrance is at the east end of the road, and the player starts at the west end.
+        let stairs_idx = self.map.xy_idx(self.map.width - 3, road_y);
+        self.map.tiles[stairs_idx] = TileType::DownStairs;
+        self.starting_position = Position { x: 2, y: road_y };
+        self.take_snapshot();
+
+        self.spawn_townsfolk(road_y, rng);
+        self.map.populate_blocked();
+    }
+
+    /// Build a row of buildings along one side of the main road, with a road from each door to it.
+    ///
+    /// edge_y is the row nearest the main road that buildings can reach, and north is which side of the road they're on.
+    fn build_street(&mut self, edge_y: i32, north: bool, rng: &mut RandomNumberGenerator) {
+        let mut x = rng.range(2, 5);
+        loop {
+            let w = rng.range(6, 12);
+            let h = rng.range(4, 10);
+            if x + w + 1 >= self.map.width - ENTRANCE_CLEARING {
+                break;
+            }
+
+            // Set the building back from the road a little, so the street isn't a straight wall.
+            let setback = rng.range(0, 3);
+            let y = if north {
+                edge_y - setback - h - 1
+            } else {
+                edge_y + setback
+            };
+            let building = Rect::new(x, y, w, h);
+            self.build_building(&building, north, edge_y, rng);
+
+            x += w + 2 + rng.range(1, 4);
+        }
+    }
+
+    /// Wall in a building, floor it, and put a door facing the main road with a path out to it.
+    fn build_building(
+        &mut self,
+        building: &Rect,
+        north: bool,
+        edge_y: i32,
+        rng: &mut RandomNumberGenerator,
+    ) {
+        for y in building.y1..=building.y2 + 1 {
+            for x in building.x1..=building.x2 + 1 {
+                let idx = self.map.xy_idx(x, y);
+                self.map.tiles[idx] = TileType::Wall;
+            }
+        }
+        apply_room_to_map(&mut self.map, building);
+        self.map.rooms.push(*building);
+
+        let door_x = rng.range(building.x1 + 1, building.x2 + 1);
+        let (door_y, path) = if north {
+            (building.y2 + 1, building.y2 + 2..=edge_y)
+        } else {
+            (building.y1, edge_y..=building.y1 - 1)
+        };
+        let door_idx = self.map.xy_idx(door_x, door_y);
+        self.map.tiles[door_idx] = TileType::ClosedDoor;
+        for y in path {
+            let idx = self.map.xy_idx(door_x, y);
+            self.map.tiles[idx] = TileType::Road;
+        }
+        self.take_snapshot();
+    }
+
+    /// Put a vendor in some buildings, and townsfolk in the rest and out on the streets.
+    fn spawn_townsfolk(&mut self, road_y: i32, rng: &mut RandomNumberGenerator) {
+        // Shuffle the buildings so vendors aren't always in the same order along the street.
+        let mut buildings = self.map.rooms.clone();
+        let mut shuffled: Vec<Rect> = Vec::new();
+        while !buildings.is_empty() {
+            let i = rng.range(0, buildings.len() as i32) as usize;
+            shuffled.push(buildings.remove(i));
+        }
+
+        for (i, building) in shuffled.iter().enumerate() {
+            let (x, y) = building.center();
+            let name = match VENDORS.get(i) {
+                Some(vendor) => vendor.to_string(),
+                None => "Townsperson".to_string(),
+            };
+            self.spawn_list.push((self.map.xy_idx(x, y), name));
+        }
+
+        let street_folk = rng.range(4, 8);
+        for _ in 0..street_folk {
+            let x = rng.range(6, self.map.width - ENTRANCE_CLEARING);
+            let y = rng.range(road_y - 1, road_y + 2);
+            let idx = self.map.xy_idx(x, y);
+            if !self.spawn_list.iter().any(|spawn| spawn.0 == idx) {
+                self.spawn_list.push((idx, "Townsperson".to_string()));
+            }
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! Checks run on every newly built level, so a broken one can be thrown away and built again.
+
+use super::common::{is_standable, populate_blocked_for_generation, walking_distances};
+use crate::{Map, Position, TileType};
+use std::fmt;
+
+/// Smallest share of the map, in percent, that must be ground the player can stand on.
+const MIN_FLOOR_PERCENT: usize = 10;
+
+/// Fewest rooms a level laid out as rooms can have, one for the player to start in and one for the stairs.
+const MIN_ROOMS: usize = 2;
+
+/// Reason a newly built level was rejected.
+#[derive(PartialEq, Clone, Debug)]
+pub enum MapValidationError {
+    /// The starting position is off the map, or somewhere the player can't stand.
+    BadStart,
+    /// There are no stairs down.
+    NoStairs,
+    /// None of the stairs down can be reached from the starting position.
+    StairsUnreachable,
+    /// Too little of the map is ground the player can stand on. Holds the percentage that is.
+    TooLittleFloor(usize),
+    /// The level is laid out as rooms, but has too few of them. Holds the number it has.
+    TooFewRooms(usize),
+}
+
+impl fmt::Display for MapValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapValidationError::BadStart => write!(f, "the player can't stand at the start"),
+            MapValidationError::NoStairs => write!(f, "there are no stairs down"),
+            MapValidationError::StairsUnreachable => {
+                write!(f, "the stairs down can't be reached from the start")
+            }
+            MapValidationError::TooLittleFloor(percent) => write!(
+                f,
+                "only {}% of the map is floor, below the minimum of {}%",
+                percent, MIN_FLOOR_PERCENT
+            ),
+            MapValidationError::TooFewRooms(rooms) => write!(
+                f,
+                "only {} rooms were built, below the minimum of {}",
+                rooms, MIN_ROOMS
+            ),
+        }
+    }
+}
+
+/// Check a newly built level is playable.
+///
+/// The player must be able to walk from the start to the stairs down, enough of the map must be floor, and levels laid out as rooms (room_based) need enough of them.
+/// Secret doors count as open, since the player can always search for them.
+pub fn validate_map(
+    map: &Map,
+    start: &Position,
+    room_based: bool,
+) -> Result<(), MapValidationError> {
+    if start.x < 1 || start.x > map.width - 2 || start.y < 1 || start.y > map.height - 2 {
+        return Err(MapValidationError::BadStart);
+    }
+    let start_idx = map.xy_idx(start.x, start.y);
+    if !is_standable(map.tiles[start_idx]) {
+        return Err(MapValidationError::BadStart);
+    }
+
+    if room_based && map.rooms.len() < MIN_ROOMS {
+        return Err(MapValidationError::TooFewRooms(map.rooms.len()));
+    }
+
+    let floor_tiles = map.tiles.iter().filter(|tile| is_standable(**tile)).count();
+    let floor_percent = floor_tiles * 100 / map.tiles.len();
+    if floor_percent < MIN_FLOOR_PERCENT {
+        return Err(MapValidationError::TooLittleFloor(floor_percent));
+    }
+
+    if !map.tiles.contains(&TileType::DownStairs) {
+        return Err(MapValidationError::NoStairs);
+    }
+    if path_to_stairs_length(map, start).is_none() {
+        return Err(MapValidationError::StairsUnreachable);
+    }
+
+    Ok(())
+}
+
+/// Number of steps on the shortest walk from start to the nearest stairs down, or None if there's no way there.
+///
+/// Secret doors count as open, as in validate_map.
+pub fn path_to_stairs_length(map: &Map, start: &Position) -> Option<usize> {
+    let mut walkable = map.clone();
+    populate_blocked_for_generation(&mut walkable);
+    let distances = walking_distances(&walkable, walkable.xy_idx(start.x, start.y));
+
+    walkable
+        .tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, tile)| **tile == TileType::DownStairs)
+        .filter_map(|(stairs_idx, _)| distances[stairs_idx])
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_builders::{MAX_BUILD_ATTEMPTS, MapBuilder, build_until_valid};
+    use crate::rect::Rect;
+    use rltk::RandomNumberGenerator;
+
+    /// A 10x10 level of open floor inside a wall, with the stairs down in the bottom-right corner.
+    fn open_map() -> Map {
+        let mut map = Map::new(1, 10, 10);
+        for y in 1..9 {
+            for x in 1..9 {
+                let idx = map.xy_idx(x, y);
+                map.tiles[idx] = TileType::Floor;
+            }
+        }
+        let stairs_idx = map.xy_idx(8, 8);
+        map.tiles[stairs_idx] = TileType::DownStairs;
+        map
+    }
+
+    /// The open map split in two by a wall running top to bottom, with the start and stairs on either side.
+    fn split_map() -> Map {
+        let mut map = open_map();
+        for y in 1..9 {
+            let idx = map.xy_idx(5, y);
+            map.tiles[idx] = TileType::Wall;
+        }
+        map
+    }
+
+    const START: Position = Position { x: 1, y: 1 };
+
+    #[test]
+    fn open_level_passes() {
+        assert_eq!(validate_map(&open_map(), &START, false), Ok(()));
+    }
+
+    #[test]
+    fn start_off_the_map_or_in_a_wall_is_rejected() {
+        let map = open_map();
+        assert_eq!(
+            validate_map(&map, &Position { x: 0, y: 0 }, false),
+            Err(MapValidationError::BadStart)
+        );
+        assert_eq!(
+            validate_map(&split_map(), &Position { x: 5, y: 3 }, false),
+            Err(MapValidationError::BadStart)
+        );
+    }
+
+    #[test]
+    fn level_without_stairs_is_rejected() {
+        let mut map = open_map();
+        let stairs_idx = map.xy_idx(8, 8);
+        map.tiles[stairs_idx] = TileType::Floor;
+        assert_eq!(
+            validate_map(&map, &START, false),
+            Err(MapValidationError::NoStairs)
+        );
+    }
+
+    #[test]
+    fn walled_off_stairs_are_rejected() {
+        assert_eq!(
+            validate_map(&split_map(), &START, false),
+            Err(MapValidationError::StairsUnreachable)
+        );
+    }
+
+    #[test]
+    fn stairs_behind_a_secret_door_pass() {
+        let mut map = split_map();
+        let door_idx = map.xy_idx(5, 4);
+        map.tiles[door_idx] = TileType::SecretDoor;
+        assert_eq!(validate_map(&map, &START, false), Ok(()));
+    }
+
+    #[test]
+    fn level_with_little_floor_is_rejected() {
+        // A short corridor on a 20x20 map is 6 tiles of 400.
+        let mut map = Map::new(1, 20, 20);
+        for x in 1..=5 {
+            let idx = map.xy_idx(x, 1);
+            map.tiles[idx] = TileType::Floor;
+        }
+        let stairs_idx = map.xy_idx(6, 1);
+        map.tiles[stairs_idx] = TileType::DownStairs;
+        assert_eq!(
+            validate_map(&map, &START, false),
+            Err(MapValidationError::TooLittleFloor(1))
+        );
+    }
+
+    #[test]
+    fn room_based_level_needs_enough_rooms() {
+        let mut map = open_map();
+        assert_eq!(
+            validate_map(&map, &START, true),
+            Err(MapValidationError::TooFewRooms(0))
+        );
+
+        map.rooms.push(Rect::new(0, 0, 4, 4));
+        assert_eq!(
+            validate_map(&map, &START, true),
+            Err(MapValidationError::TooFewRooms(1))
+        );
+
+        map.rooms.push(Rect::new(4, 4, 4, 4));
+        assert_eq!(validate_map(&map, &START, true), Ok(()));
+        // Levels that aren't room based don't need any.
+        assert_eq!(validate_map(&open_map(), &START, false), Ok(()));
+    }
+
+    /// Builder that always builds the same level, walled off from its stairs.
+    struct SplitBuilder {
+        history: Vec<Map>,
+        spawn_list: Vec<(usize, String)>,
+    }
+
+    impl MapBuilder for SplitBuilder {
+        fn build_map(&mut self, _rng: &mut RandomNumberGenerator) {}
+
+        fn get_map(&self) -> Map {
+            split_map()
+        }
+
+        fn get_starting_position(&self) -> Position {
+            START
+        }
+
+        fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+            &self.spawn_list
+        }
+
+        fn get_snapshot_history(&self) -> &Vec<Map> {
+            &self.history
+        }
+
+        fn take_snapshot(&mut self) {}
+    }
+
+    #[test]
+    fn building_gives_up_after_max_attempts() {
+        let mut attempts = 0;
+        let builder = build_until_valid(1, &mut RandomNumberGenerator::seeded(1), |_, _| {
+            attempts += 1;
+            Box::new(SplitBuilder {
+                history: Vec::new(),
+                spawn_list: Vec::new(),
+            })
+        });
+
+        assert_eq!(attempts, MAX_BUILD_ATTEMPTS);
+        // The last attempt is kept, broken as it is.
+        assert_eq!(
+            validate_map(&builder.get_map(), &builder.get_starting_position(), false),
+            Err(MapValidationError::StairsUnreachable)
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! Map builder using wave function collapse (WFC) over tile patterns learned from another map.
+
+use super::{
+    MapBuilder,
+    common::{
+        generate_voronoi_areas, record_snapshot, remove_unreachable_areas_returning_most_distant,
+    },
+    validation::validate_map,
+};
+use crate::{
+    Map, Position, TileType,
+    map::{MAPHEIGHT, MAPWIDTH},
+    spawner,
+};
+use rltk::RandomNumberGenerator;
+use std::collections::HashSet;
+
+/// Width and height of the square tile patterns chopped out of the source map.
+const CHUNK_SIZE: i32 = 8;
+/// Number of times to re-run the solver after a contradiction before falling back to the source map.
+const MAX_ATTEMPTS: i32 = 10;
+
+/// Offsets to the neighboring chunk to the north, east, south, and west.
+const DIRECTIONS: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+/// A square tile pattern learned from the source map, and how often it shows up there.
+struct Pattern {
+    tiles: Vec<TileType>,
+    frequency: i32,
+}
+
+impl Pattern {
+    /// Floor/wall tiles along one edge, in the order north, east, south, west.
+    fn edge(&self, direction: usize) -> Vec<bool> {
+        let n = CHUNK_SIZE as usize;
+        (0..n)
+            .map(|i| {
+                let (x, y) = match direction {
+                    0 => (i, 0),
+                    1 => (n - 1, i),
+                    2 => (i, n - 1),
+                    _ => (0, i),
+                };
+                self.tiles[y * n + x] == TileType::Floor
+            })
+            .collect()
+    }
+}
+
+/// Builds levels by re-synthesizing another builder's map from its local tile patterns.
+///
+/// The source map is chopped into CHUNK_SIZE patterns, patterns that share an identical edge are allowed to sit next to each other, and a new level is collapsed one chunk at a time under those constraints.
+pub struct WaveformCollapseBuilder {
+    map: Map,
+    starting_position: Position,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Map>,
+    previous_builder: Box<dyn MapBuilder>,
+}
+
+impl MapBuilder for WaveformCollapseBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        &self.spawn_list
+    }
+
+    fn get_snapshot_history(&self) -> &Vec<Map> {
+        &self.history
+    }
+
+    fn take_snapshot(&mut self) {
+        record_snapshot(&mut self.history, &self.map);
+    }
+}
+
+impl WaveformCollapseBuilder {
+    /// Level re-synthesized from the map built by previous_builder.
+    pub fn derived_map(
+        new_depth: i32,
+        previous_builder: Box<dyn MapBuilder>,
+    ) -> WaveformCollapseBuilder {
+        WaveformCollapseBuilder {
+            // Resized to match the source map once it's built.
+            map: Map::new(new_depth, MAPWIDTH, MAPHEIGHT),
+            starting_position: Position { x: 0, y: 0 },
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+            previous_builder,
+        }
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        let depth = self.map.depth;
+
+        self.previous_builder.build_map(rng);
+        let source = self.previous_builder.get_map();
+        self.history = self.previous_builder.get_snapshot_history().clone();
+        let patterns = build_patterns(&source);
+        let constraints = compute_adjacency(&patterns);
+        let chunks_x = source.width / CHUNK_SIZE;
+        let chunks_y = source.height / CHUNK_SIZE;
+
+        for _ in 0..MAX_ATTEMPTS {
+            let Some(solution) = solve(&patterns, &constraints, chunks_x, chunks_y, rng) else {
+                continue;
+            };
+
+            self.map = Map::new(depth, source.width, source.height);
+            self.apply_solution(&patterns, &solution, chunks_x);
+            self.take_snapshot();
+            if self.place_start_and_stairs() {
+                self.take_snapshot();
+
+                // Fill each spawn area with monsters and items.
+                let spawn_areas = generate_voronoi_areas(&self.map, rng);
+                for area in spawn_areas.iter() {
+                    spawner::spawn_region(rng, area, self.map.depth, &mut self.spawn_list);
+                }
+                return;
+            }
+        }
+
+        rltk::console::log("Wave function collapse kept failing, falling back to the source map");
+        self.map = source;
+        self.starting_position = self.previous_builder.get_starting_position();
+        self.spawn_list = self.previous_builder.get_spawn_list().clone();
+    }
+
+    /// Write the solved patterns onto the map, keeping the outer edge as wall.
+    fn apply_solution(&mut self, patterns: &[Pattern], solution: &[usize], chunks_x: i32) {
+        let n = CHUNK_SIZE as usize;
+        for (cell, pattern_idx) in solution.iter().enumerate() {
+            let chunk_x = cell as i32 % chunks_x;
+            let chunk_y = cell as i32 / chunks_x;
+            for y in 0..n {
+                for x in 0..n {
+                    let map_x = chunk_x * CHUNK_SIZE + x as i32;
+                    let map_y = chunk_y * CHUNK_SIZE + y as i32;
+                    if map_x > 0
+                        && map_x < self.map.width - 1
+                        && map_y > 0
+                        && map_y < self.map.height - 1
+                    {
+                        let idx = self.map.xy_idx(map_x, map_y);
+                        self.map.tiles[idx] = patterns[*pattern_idx].tiles[y * n + x];
+                    }
+                }
+            }
+        }
+    }
+
+    /// Start the player at the floor tile closest to the middle of the map, cull what they can't reach, and put the stairs down as far away as possible.
+    ///
+    /// Returns false if what's left fails the same validation as every other level, so the solve is retried instead of the whole level being rebuilt.
+    fn place_start_and_stairs(&mut self) -> bool {
+        let center = rltk::Point::new(self.map.width / 2, self.map.height / 2);
+        let start_idx = self
+            .map
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| **tile == TileType::Floor)
+            .map(|(idx, _)| idx)
+            .min_by_key(|idx| {
+                let x = *idx as i32 % self.map.width;
+                let y = *idx as i32 / self.map.width;
+                i32::abs(x - center.x) + i32::abs(y - center.y)
+            });
+        let Some(start_idx) = start_idx else {
+            return false;
+        };
+        self.starting_position = Position {
+            x: start_idx as i32 % self.map.width,
+            y: start_idx as i32 / self.map.width,
+        };
+
+        let exit_tile = remove_unreachable_areas_returning_most_distant(&mut self.map, start_idx);
+        if exit_tile == start_idx {
+            return false;
+        }
+        self.map.tiles[exit_tile] = TileType::DownStairs;
+
+        validate_map(&self.map, &self.starting_position, false).is_ok()
+    }
+}
+
+/// Chop the source map into CHUNK_SIZE patterns, including their mirror images, counting how often each appears.
+fn build_patterns(source: &Map) -> Vec<Pattern> {
+    let n = CHUNK_SIZE as usize;
+    let mut patterns: Vec<Pattern> = Vec::new();
+
+    for chunk_y in 0..source.height / CHUNK_SIZE {
+        for chunk_x in 0..source.width / CHUNK_SIZE {
+            // Stairs are placed fresh on the new level, so treat them as floor.
+            let mut tiles = Vec::with_capacity(n * n);
+            for y in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    let idx = source.xy_idx(chunk_x * CHUNK_SIZE + x, chunk_y * CHUNK_SIZE + y);
+                    tiles.push(match source.tiles[idx] {
+                        TileType::Wall => TileType::Wall,
+                        _ => TileType::Floor,
+                    });
+                }
+            }
+
+            let flip = |flip_x: bool, flip_y: bool| -> Vec<TileType> {
+                let mut flipped = tiles.clone();
+                for y in 0..n {
+                    for x in 0..n {
+                        let src_x = if flip_x { n - 1 - x } else { x };
+                        let src_y = if flip_y { n - 1 - y } else { y };
+                        flipped[y * n + x] = tiles[src_y * n + src_x];
+                    }
+                }
+                flipped
+            };
+
+            for variant in [
+                flip(false, false),
+                flip(true, false),
+                flip(false, true),
+                flip(true, true),
+            ] {
+                match patterns.iter_mut().find(|p| p.tiles == variant) {
+                    Some(existing) => existing.frequency += 1,
+                    None => patterns.push(Pattern {
+                        tiles: variant,
+                        frequency: 1,
+                    }),
+                }
+            }
+        }
+    }
+
+    patterns
+}
+
+/// For each pattern and direction, list the patterns allowed next to it: those whose facing edge is identical.
+fn compute_adjacency(patterns: &[Pattern]) -> Vec<[Vec<usize>; 4]> {
+    let edges: Vec<[Vec<bool>; 4]> = patterns
+        .iter()
+        .map(|p| [p.edge(0), p.edge(1), p.edge(2), p.edge(3)])
+        .collect();
+
+    edges
+        .iter()
+        .map(|edge| {
+            std::array::from_fn(|direction| {
+                let opposite = (direction + 2) % 4;
+                edges
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, other)| other[opposite] == edge[direction])
+                    .map(|(idx, _)| idx)
+                    .collect()
+            })
+        })
+        .collect()
+}
+
+/// Collapse a chunks_x by chunks_y grid of patterns, always collapsing the cell with the fewest options next.
+///
+/// Returns the chosen pattern for every cell, or None on a contradiction.
+fn solve(
+    patterns: &[Pattern],
+    constraints: &[[Vec<usize>; 4]],
+    chunks_x: i32,
+    chunks_y: i32,
+    rng: &mut RandomNumberGenerator,
+) -> Option<Vec<usize>> {
+    let cell_count = (chunks_x * chunks_y) as usize;
+    let mut options: Vec<Vec<usize>> = vec![(0..patterns.len()).collect(); cell_count];
+
+    // Drop patterns that can never fit next to anything before choosing.
+    for cell in 0..cell_count {
+        if !propagate(&mut options, constraints, cell, chunks_x, chunks_y) {
+            return None;
+        }
+    }
+
+    loop {
+        let mut lowest_entropy: Option<usize> = None;
+        for (cell, cell_options) in options.iter().enumerate() {
+            if cell_options.len() > 1
+                && lowest_entropy.is_none_or(|lowest| cell_options.len() < options[lowest].len())
+            {
+                lowest_entropy = Some(cell);
+            }
+        }
+        let Some(cell) = lowest_entropy else {
+            break;
+        };
+
+        // Pick one of the remaining patterns, weighted by how common it was in the source.
+        let total_weight: i32 = options[cell].iter().map(|p| patterns[*p].frequency).sum();
+        let mut roll = rng.roll_dice(1, total_weight) - 1;
+        let mut choice = options[cell][0];
+        for p in options[cell].iter() {
+            if roll < patterns[*p].frequency {
+                choice = *p;
+                break;
+            }
+            roll -= patterns[*p].frequency;
+        }
+        options[cell] = vec![choice];
+
+        if !propagate(&mut options, constraints, cell, chunks_x, chunks_y) {
+            return None;
+        }
+    }
+
+    Some(options.iter().map(|o| o[0]).collect())
+}
+
+/// Narrow the options of neighboring cells to patterns compatible with a changed cell, spreading outward as cells change.
+///
+/// Returns false if any cell runs out of options.
+fn propagate(
+    options: &mut [Vec<usize>],
+    constraints: &[[Vec<usize>; 4]],
+    start_cell: usize,
+    chunks_x: i32,
+    chunks_y: i32,
+) -> bool {
+    let mut changed: Vec<usize> = vec![start_cell];
+    while let Some(cell) = changed.pop() {
+        let cell_x = cell as i32 % chunks_x;
+        let cell_y = cell as i32 / chunks_x;
+        for (direction, (dx, dy)) in DIRECTIONS.iter().enumerate() {
+            let (nx, ny) = (cell_x + dx, cell_y + dy);
+            if nx < 0 || nx >= chunks_x || ny < 0 || ny >= chunks_y {
+                continue;
+            }
+            let neighbor = (ny * chunks_x + nx) as usize;
+
+            let allowed: HashSet<usize> = options[cell]
+                .iter()
+                .flat_map(|p| constraints[*p][direction].iter().copied())
+                .collect();
+            let before = options[neighbor].len();
+            options[neighbor].retain(|p| allowed.contains(p));
+            if options[neighbor].is_empty() {
+                return false;
+            }
+            if options[neighbor].len() != before {
+                changed.push(neighbor);
+            }
+        }
+    }
+    true
+}
@@ -0,0 +1,251 @@
+//! Headless level generation, for looking over many levels without playing them.
+//!
+//! Run as `absolutechaos mapgen [--seed <number>] [--depth <number>] [--builder <name>] [--count <number>] [--width <number>] [--height <number>] [--format ascii|json] [--out <path>]`.
+//! Without a builder the level is built the way the game builds it, theme, decorations, validation and all. Naming a builder builds just that one, once.
+//! ASCII maps and their statistics are printed. JSON writes every map, with its statistics, to the --out file, keeping it clear of generator log lines.
+
+use crate::{
+    Map, Position, TileType,
+    map::{MAPHEIGHT, MAPWIDTH},
+    map_builders::{self, MapBuilder},
+    run_seed::RunSeed,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+
+/// JSON is written here unless another path is given with --out.
+const DEFAULT_JSON_PATH: &str = "./mapgen.json";
+
+/// How built levels are written out.
+#[derive(PartialEq, Copy, Clone)]
+enum OutputFormat {
+    Ascii,
+    Json,
+}
+
+/// Summary statistics for a built level.
+#[derive(Serialize)]
+struct MapStats {
+    seed: u64,
+    depth: i32,
+    builder: String,
+    width: i32,
+    height: i32,
+    /// Share of the map, in percent, that the player can stand on, counted as validation counts it.
+    floor_percent: f32,
+    rooms: usize,
+    /// Steps on the shortest walk from the start to the stairs down, if there is one.
+    path_to_stairs: Option<usize>,
+    /// Number of each monster, item, and trap queued to spawn.
+    spawns: BTreeMap<String, usize>,
+    /// Why validation rejects the level, if it does.
+    validation_error: Option<String>,
+}
+
+/// Built level and its statistics, as written to JSON.
+#[derive(Serialize)]
+struct MapDump {
+    stats: MapStats,
+    map: Map,
+}
+
+/// Value following flag on the command line, if any.
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
+/// Number following flag on the command line, or default if it's missing or isn't a number.
+fn arg_number(args: &[String], flag: &str, default: i32) -> i32 {
+    match arg_value(args, flag) {
+        None => default,
+        Some(value) => value.parse::<i32>().unwrap_or_else(|_| {
+            eprintln!("Ignoring invalid {} '{}'", flag, value);
+            default
+        }),
+    }
+}
+
+/// Smallest width or height a level can be built at, leaving room for a few rooms inside the outer wall.
+const MIN_MAP_SIZE: i32 = 20;
+
+/// Build levels as asked on the command line and write them out.
+pub fn run() {
+    let args: Vec<String> = std::env::args().collect();
+    let run_seed = RunSeed::from_args().unwrap_or_else(RunSeed::random);
+    let depth = arg_number(&args, "--depth", 1);
+    let count = arg_number(&args, "--count", 1);
+    let width = arg_number(&args, "--width", MAPWIDTH);
+    let height = arg_number(&args, "--height", MAPHEIGHT);
+    let builder_name = arg_value(&args, "--builder");
+    let format = match arg_value(&args, "--format").as_deref() {
+        None | Some("ascii") => OutputFormat::Ascii,
+        Some("json") => OutputFormat::Json,
+        Some(other) => {
+            eprintln!("Unknown format '{}', expected ascii or json", other);
+            return;
+        }
+    };
+    if let Some(name) = &builder_name
+        && !map_builders::BUILDER_NAMES.contains(&name.as_str())
+    {
+        eprintln!(
+            "Unknown builder '{}', expected one of: {}",
+            name,
+            map_builders::BUILDER_NAMES.join(", ")
+        );
+        return;
+    }
+    if builder_name.is_none() {
+        if arg_value(&args, "--width").is_some() || arg_value(&args, "--height").is_some() {
+            eprintln!("Ignoring --width and --height, which only apply with --builder");
+        }
+    } else if width < MIN_MAP_SIZE || height < MIN_MAP_SIZE {
+        eprintln!(
+            "Map size {}x{} is too small, expected at least {}x{}",
+            width, height, MIN_MAP_SIZE, MIN_MAP_SIZE
+        );
+        return;
+    }
+
+    let mut dumps: Vec<MapDump> = Vec::new();
+    for i in 0..count {
+        // Consecutive seeds, so a batch can be rerun one level at a time.
+        let seed = run_seed.seed + i as u64;
+        let mut rng = RunSeed { seed }.level_rng(depth);
+        let builder: Box<dyn MapBuilder> = match &builder_name {
+            None => map_builders::build_level(depth, &mut rng),
+            Some(name) => {
+                let mut builder = map_builders::named_builder(name, depth, width, height)
+                    .expect("Unable to find builder");
+                builder.build_map(&mut rng);
+                builder
+            }
+        };
+
+        let map = builder.get_map();
+        let start = builder.get_starting_position();
+        let stats = map_stats(
+            &*builder,
+            seed,
+            depth,
+            builder_name.as_deref().unwrap_or("random"),
+        );
+        match format {
+            OutputFormat::Ascii => {
+                println!("{}", map_to_ascii(&map, &start));
+                print_stats(&stats);
+            }
+            OutputFormat::Json => {
+                print_stats(&stats);
+                dumps.push(MapDump { stats, map });
+            }
+        }
+    }
+
+    if format == OutputFormat::Json {
+        let path = arg_value(&args, "--out").unwrap_or_else(|| DEFAULT_JSON_PATH.to_string());
+        let writer = File::create(&path).expect("Unable to create JSON file");
+        serde_json::to_writer(writer, &dumps).expect("Unable to write JSON");
+        println!("Wrote {} maps to {}", dumps.len(), path);
+    }
+}
+
+/// Work out the summary statistics for a built level.
+fn map_stats(builder: &dyn MapBuilder, seed: u64, depth: i32, builder_name: &str) -> MapStats {
+    let map = builder.get_map();
+    let start = builder.get_starting_position();
+
+    let floor_tiles = map
+        .tiles
+        .iter()
+        .filter(|tile| map_builders::is_standable(**tile))
+        .count();
+    let mut spawns: BTreeMap<String, usize> = BTreeMap::new();
+    // Spawn tables can roll "None", for a spot left empty.
+    for (_idx, name) in builder
+        .get_spawn_list()
+        .iter()
+        .filter(|spawn| spawn.1 != "None")
+    {
+        *spawns.entry(name.clone()).or_insert(0) += 1;
+    }
+
+    MapStats {
+        seed,
+        depth,
+        builder: builder_name.to_string(),
+        width: map.width,
+        height: map.height,
+        floor_percent: floor_tiles as f32 * 100.0 / map.tiles.len() as f32,
+        rooms: map.rooms.len(),
+        path_to_stairs: map_builders::path_to_stairs_length(&map, &start),
+        spawns,
+        validation_error: map_builders::validate_map(&map, &start, builder.is_room_based())
+            .err()
+            .map(|reason| reason.to_string()),
+    }
+}
+
+/// Print a level's statistics on a few lines.
+fn print_stats(stats: &MapStats) {
+    println!(
+        "seed {} depth {} builder {}: {}x{}, {:.1}% floor, {} rooms, {} steps to the stairs",
+        stats.seed,
+        stats.depth,
+        stats.builder,
+        stats.width,
+        stats.height,
+        stats.floor_percent,
+        stats.rooms,
+        stats
+            .path_to_stairs
+            .map_or("no path".to_string(), |steps| steps.to_string()),
+    );
+    let spawns: Vec<String> = stats
+        .spawns
+        .iter()
+        .map(|(name, count)| format!("{} {}", count, name))
+        .collect();
+    println!("  spawns: {}", spawns.join(", "));
+    if let Some(reason) = &stats.validation_error {
+        println!("  invalid: {}", reason);
+    }
+}
+
+/// Draw a level in plain ASCII, one line per row, with the player's start as `@`.
+///
+/// Uses the prefab glyphs where there is one, and shows secret doors as `S`.
+fn map_to_ascii(map: &Map, start: &Position) -> String {
+    let mut ascii = String::new();
+    for y in 0..map.height {
+        for x in 0..map.width {
+            if x == start.x && y == start.y {
+                ascii.push('@');
+                continue;
+            }
+            ascii.push(match map.tiles[map.xy_idx(x, y)] {
+                TileType::Wall => '#',
+                TileType::Floor => '.',
+                TileType::DownStairs => '>',
+                TileType::UpStairs => '<',
+                TileType::ClosedDoor => '+',
+                TileType::OpenDoor => '\'',
+                TileType::SecretDoor => 'S',
+                TileType::ShallowWater => '~',
+                TileType::DeepWater => 'W',
+                TileType::Lava => 'L',
+                TileType::Rubble => ';',
+                TileType::Bridge => '=',
+                TileType::Chasm => '_',
+                TileType::Road => ':',
+                TileType::Grass => '"',
+            });
+        }
+        ascii.push('\n');
+    }
+    ascii
+}
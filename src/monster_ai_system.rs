@@ -1,9 +1,17 @@
 //! Logic for monster AI system.
 
-use crate::{Map, Monster, Position, RunState, Viewshed, WantsToMelee, components::Confusion};
+use crate::{
+    Map, Monster, Name, Position, RunState, TileType, Viewshed, WantsToMelee,
+    components::{Alerted, Confusion, EntityMoved, Slowed, Swimmer, WarCry},
+    gamelog::GameLog,
+    map::SwimmingView,
+};
 use rltk::Point;
 use specs::prelude::*;
 
+/// Number of turns monsters stay alerted to the player after a war cry.
+const WAR_CRY_TURNS: i32 = 20;
+
 /// System for monster NPC AI for an ECS.
 pub struct MonsterAI {}
 
@@ -20,6 +28,13 @@ impl<'a> System<'a> for MonsterAI {
         WriteStorage<'a, Position>,
         WriteStorage<'a, WantsToMelee>,
         WriteStorage<'a, Confusion>,
+        WriteStorage<'a, Alerted>,
+        WriteStorage<'a, EntityMoved>,
+        WriteStorage<'a, Slowed>,
+        ReadStorage<'a, Swimmer>,
+        WriteStorage<'a, WarCry>,
+        ReadStorage<'a, Name>,
+        WriteExpect<'a, GameLog>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -34,6 +49,13 @@ impl<'a> System<'a> for MonsterAI {
             mut position,
             mut wants_to_melee,
             mut confused,
+            mut alerted,
+            mut entity_moved,
+            mut slowed,
+            swimmers,
+            mut war_cries,
+            names,
+            mut log,
         ) = data;
 
         // Only run if it's monster's turn.
@@ -41,6 +63,9 @@ impl<'a> System<'a> for MonsterAI {
             return;
         }
 
+        let mut door_opened = false;
+        let mut war_criers: Vec<Entity> = Vec::new();
+
         for (entity, viewshed, _monster, pos) in
             (&entities, &mut viewshed, &monster, &mut position).join()
         {
@@ -54,7 +79,30 @@ impl<'a> System<'a> for MonsterAI {
                 }
                 can_act = false;
             }
+
+            // Wading through water costs a turn.
+            if slowed.remove(entity).is_some() {
+                can_act = false;
+            }
+
+            // Alerted monsters know where the player is even when they can't see them.
+            let mut is_alerted = false;
+            if let Some(alert) = alerted.get_mut(entity) {
+                alert.turns -= 1;
+                if alert.turns < 1 {
+                    alerted.remove(entity);
+                }
+                is_alerted = true;
+            }
+
             if can_act {
+                // A war cry goes up the first time the player is seen.
+                if viewshed.visible_tiles.contains(&*player_pos)
+                    && war_cries.remove(entity).is_some()
+                {
+                    war_criers.push(entity);
+                }
+
                 let distance =
                     rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
                 if distance < 1.5 {
@@ -66,24 +114,61 @@ impl<'a> System<'a> for MonsterAI {
                             },
                         )
                         .expect("Unaable to insert attack");
-                } else if viewshed.visible_tiles.contains(&*player_pos) {
+                } else if is_alerted || viewshed.visible_tiles.contains(&*player_pos) {
                     // Path to player
-                    let path = rltk::a_star_search(
-                        map.xy_idx(pos.x, pos.y),
-                        map.xy_idx(player_pos.x, player_pos.y),
-                        &mut *map,
-                    );
+                    let start = map.xy_idx(pos.x, pos.y);
+                    let end = map.xy_idx(player_pos.x, player_pos.y);
+                    let path = if swimmers.get(entity).is_some() {
+                        rltk::a_star_search(start, end, &SwimmingView { map: &map })
+                    } else {
+                        rltk::a_star_search(start, end, &*map)
+                    };
                     if path.success && path.steps.len() > 1 {
-                        let mut idx = map.xy_idx(pos.x, pos.y);
-                        map.blocked[idx] = false;
-                        pos.x = path.steps[1] as i32 % map.width;
-                        pos.y = path.steps[1] as i32 / map.width;
-                        idx = map.xy_idx(pos.x, pos.y);
-                        map.blocked[idx] = true;
-                        viewshed.dirty = true;
+                        if map.tiles[path.steps[1]] == TileType::ClosedDoor {
+                            // Open the door in the way instead of moving.
+                            map.tiles[path.steps[1]] = TileType::OpenDoor;
+                            door_opened = true;
+                        } else if !map.tiles[path.steps[1]].is_harmful() {
+                            // Anything harmful is only on the path when there's no way around, so wait it out.
+                            let mut idx = map.xy_idx(pos.x, pos.y);
+                            map.blocked[idx] = false;
+                            pos.x = path.steps[1] as i32 % map.width;
+                            pos.y = path.steps[1] as i32 / map.width;
+                            idx = map.xy_idx(pos.x, pos.y);
+                            map.blocked[idx] = true;
+                            viewshed.dirty = true;
+                            entity_moved
+                                .insert(entity, EntityMoved {})
+                                .expect("Unable to insert marker");
+                        }
                     }
                 }
             }
         }
+
+        // Every monster on the level comes running at a war cry.
+        for crier in war_criers {
+            if let Some(name) = names.get(crier) {
+                log.entries
+                    .push(format!("{} lets out a war cry!", name.name));
+            }
+            for (monster_entity, _monster, _pos) in (&entities, &monster, &position).join() {
+                alerted
+                    .insert(
+                        monster_entity,
+                        Alerted {
+                            turns: WAR_CRY_TURNS,
+                        },
+                    )
+                    .expect("Unable to insert alert");
+            }
+        }
+
+        // Opening a door changes what everyone can see.
+        if door_opened {
+            for viewshed in (&mut viewshed).join() {
+                viewshed.dirty = true;
+            }
+        }
     }
 }
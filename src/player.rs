@@ -1,23 +1,31 @@
 use crate::{
-    CombatStats, Map, Monster, Player, Position, RunState, State, TileType, Viewshed, WantsToMelee,
-    components::{Item, WantsToPickupItem},
+    CombatStats, Map, Monster, Name, Player, Position, RunState, State, TileType, Viewshed,
+    WantsToMelee,
+    components::{EntityMoved, Item, Slowed, Swimmer, Vendor, WantsToPickupItem},
     gamelog::GameLog,
 };
-use rltk::{Point, Rltk, VirtualKeyCode};
+use rltk::{Point, RandomNumberGenerator, Rltk, VirtualKeyCode};
 use specs::{Entity, Join, World, WorldExt};
-use std::cmp::{max, min};
 
 /// Attempt to change players position on map, respecting walls.
 ///
-/// If players are obstructed, change is ignored. Marks viewshed tiles as dirty. Updates player position resource in ECS. Movement also is used to attack.
-fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+/// If players are obstructed, change is ignored. Marks viewshed tiles as dirty. Updates player position resource in ECS. Movement also is used to attack, and to open closed doors.
+/// Deep water stops players who can't swim, and stepping into a chasm drops them to the next level.
+/// Bumping into a vendor passes the time of day instead of attacking.
+fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState {
     let mut positions = ecs.write_storage::<Position>();
     let players = ecs.write_storage::<Player>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
     let combat_stats = ecs.read_storage::<CombatStats>();
-    let map = ecs.fetch::<Map>();
+    let mut map = ecs.fetch_mut::<Map>();
     let entities = ecs.entities();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
+    let mut entity_moved = ecs.write_storage::<EntityMoved>();
+    let swimmers = ecs.read_storage::<Swimmer>();
+    let vendors = ecs.read_storage::<Vendor>();
+    let names = ecs.read_storage::<Name>();
+    let mut door_opened = false;
+    let mut fell = false;
 
     for (entity, _player, pos, viewshed) in
         (&entities, &players, &mut positions, &mut viewsheds).join()
@@ -28,11 +36,23 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             || pos.y + delta_y < 1
             || pos.y + delta_y > map.height - 1
         {
-            return;
+            return RunState::PlayerTurn;
         }
         let destination_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
 
         for potential_target in map.tile_content[destination_idx].iter() {
+            // Vendors greet the player instead of being attacked.
+            if vendors.get(*potential_target).is_some() {
+                if let Some(name) = names.get(*potential_target) {
+                    let mut gamelog = ecs.fetch_mut::<GameLog>();
+                    gamelog.entries.push(format!(
+                        "The {} nods at you from behind the counter.",
+                        name.name
+                    ));
+                }
+                return RunState::PlayerTurn;
+            }
+
             let target = combat_stats.get(*potential_target);
             // Creates WantsToAttack component, paint the attacker with it.
             if let Some(_target) = target {
@@ -44,50 +64,100 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
                         },
                     )
                     .expect("Add target failed");
-                return;
+                return RunState::PlayerTurn;
             }
         }
 
-        if !map.blocked[destination_idx] {
-            pos.x = min(79, max(0, pos.x + delta_x));
-            pos.y = min(49, max(0, pos.y + delta_y));
+        // Bumping into a closed door opens it, using up the move.
+        if map.tiles[destination_idx] == TileType::ClosedDoor {
+            map.tiles[destination_idx] = TileType::OpenDoor;
+            door_opened = true;
+            break;
+        }
+
+        let destination_tile = map.tiles[destination_idx];
+        if !map.blocked[destination_idx]
+            && (swimmers.get(entity).is_some() || !destination_tile.needs_swimming())
+        {
+            pos.x = (pos.x + delta_x).clamp(0, map.width - 1);
+            pos.y = (pos.y + delta_y).clamp(0, map.height - 1);
 
             viewshed.dirty = true;
+            entity_moved
+                .insert(entity, EntityMoved {})
+                .expect("Unable to insert marker");
 
             // Player position resource update accessible to others.
             let mut ppos = ecs.write_resource::<Point>();
             ppos.x = pos.x;
             ppos.y = pos.y;
+
+            fell = destination_tile == TileType::Chasm;
+        }
+    }
+
+    // Opening a door changes what everyone can see.
+    if door_opened {
+        for viewshed in (&mut viewsheds).join() {
+            viewshed.dirty = true;
         }
     }
+
+    if fell {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog
+            .entries
+            .push("You fall through the chasm to the level below!".to_string());
+        return RunState::NextLevel;
+    }
+    RunState::PlayerTurn
 }
 
 /// Handle and translate user inputs to player actions, movement in tick.
 ///
 /// Accepts direction keys, vi-like movement, or numpad.
 pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
+    // Wading through water costs the player their next turn.
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    if gs
+        .ecs
+        .write_storage::<Slowed>()
+        .remove(player_entity)
+        .is_some()
+    {
+        return RunState::PlayerTurn;
+    }
+
     match ctx.key {
         None => return RunState::AwaitInput, // When nothing happened.
         Some(key) => match key {
             // Basic player movement.
             VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => {
-                try_move_player(-1, 0, &mut gs.ecs)
+                return try_move_player(-1, 0, &mut gs.ecs);
             }
             VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => {
-                try_move_player(1, 0, &mut gs.ecs)
+                return try_move_player(1, 0, &mut gs.ecs);
             }
             VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => {
-                try_move_player(0, -1, &mut gs.ecs)
+                return try_move_player(0, -1, &mut gs.ecs);
             }
             VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => {
-                try_move_player(0, 1, &mut gs.ecs)
+                return try_move_player(0, 1, &mut gs.ecs);
             }
 
             // Diagonal directions.
-            VirtualKeyCode::Numpad9 | VirtualKeyCode::U => try_move_player(1, -1, &mut gs.ecs),
-            VirtualKeyCode::Numpad7 | VirtualKeyCode::Y => try_move_player(-1, -1, &mut gs.ecs),
-            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => try_move_player(1, 1, &mut gs.ecs),
-            VirtualKeyCode::Numpad1 | VirtualKeyCode::B => try_move_player(-1, 1, &mut gs.ecs),
+            VirtualKeyCode::Numpad9 | VirtualKeyCode::U => {
+                return try_move_player(1, -1, &mut gs.ecs);
+            }
+            VirtualKeyCode::Numpad7 | VirtualKeyCode::Y => {
+                return try_move_player(-1, -1, &mut gs.ecs);
+            }
+            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => {
+                return try_move_player(1, 1, &mut gs.ecs);
+            }
+            VirtualKeyCode::Numpad1 | VirtualKeyCode::B => {
+                return try_move_player(-1, 1, &mut gs.ecs);
+            }
 
             // Pickup item.
             VirtualKeyCode::G => get_item(&mut gs.ecs),
@@ -107,6 +177,11 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
                     return RunState::NextLevel;
                 }
             }
+            VirtualKeyCode::Comma => {
+                if try_previous_level(&mut gs.ecs) {
+                    return RunState::PreviousLevel;
+                }
+            }
 
             // Skip turn.
             VirtualKeyCode::Space | VirtualKeyCode::Numpad5 => return skip_turn(&mut gs.ecs),
@@ -114,14 +189,113 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
             // Show remove item menu.
             VirtualKeyCode::R => return RunState::ShowRemoveItem,
 
+            // Close adjacent doors.
+            VirtualKeyCode::C => return close_doors(&mut gs.ecs),
+
+            // Search for secret doors.
+            VirtualKeyCode::S => return search(&mut gs.ecs),
+
             _ => return RunState::AwaitInput,
         },
     }
     RunState::PlayerTurn
 }
 
+/// Close every open door next to the player that nothing is standing in.
+///
+/// Takes a turn if any door was closed.
+fn close_doors(ecs: &mut World) -> RunState {
+    let player_pos = *ecs.fetch::<Point>();
+    let mut closed_any = false;
+    {
+        let mut map = ecs.fetch_mut::<Map>();
+        for x in player_pos.x - 1..=player_pos.x + 1 {
+            for y in player_pos.y - 1..=player_pos.y + 1 {
+                if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                    continue;
+                }
+                let idx = map.xy_idx(x, y);
+                if map.tiles[idx] == TileType::OpenDoor && map.tile_content[idx].is_empty() {
+                    map.tiles[idx] = TileType::ClosedDoor;
+                    closed_any = true;
+                }
+            }
+        }
+    }
+
+    if !closed_any {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog
+            .entries
+            .push("There is no open door here to close.".to_string());
+        return RunState::AwaitInput;
+    }
+
+    // Closing a door changes what everyone can see.
+    let mut viewsheds = ecs.write_storage::<Viewshed>();
+    for viewshed in (&mut viewsheds).join() {
+        viewshed.dirty = true;
+    }
+    RunState::PlayerTurn
+}
+
+/// One in this many secret doors next to the player is found by a search.
+const ACTIVE_SEARCH_CHANCE: i32 = 3;
+/// One in this many secret doors next to the player is noticed while they wait.
+const PASSIVE_SEARCH_CHANCE: i32 = 10;
+
+/// Roll to find each secret door next to the player, turning found ones into closed doors.
+///
+/// Returns true if any secret door was found.
+fn search_for_secret_doors(ecs: &mut World, chance: i32) -> bool {
+    let player_pos = *ecs.fetch::<Point>();
+    let mut found_any = false;
+    {
+        let mut map = ecs.fetch_mut::<Map>();
+        let mut rng = ecs.fetch_mut::<RandomNumberGenerator>();
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        for x in player_pos.x - 1..=player_pos.x + 1 {
+            for y in player_pos.y - 1..=player_pos.y + 1 {
+                if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                    continue;
+                }
+                let idx = map.xy_idx(x, y);
+                if map.tiles[idx] == TileType::SecretDoor && rng.roll_dice(1, chance) == 1 {
+                    map.tiles[idx] = TileType::ClosedDoor;
+                    gamelog.entries.push("You found a secret door!".to_string());
+                    found_any = true;
+                }
+            }
+        }
+    }
+
+    if found_any {
+        // The door shows up on the map and in the player's memory of it.
+        let mut viewsheds = ecs.write_storage::<Viewshed>();
+        for viewshed in (&mut viewsheds).join() {
+            viewshed.dirty = true;
+        }
+    }
+    found_any
+}
+
+/// Spend a turn searching the walls next to the player for secret doors.
+fn search(ecs: &mut World) -> RunState {
+    if !search_for_secret_doors(ecs, ACTIVE_SEARCH_CHANCE) {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog
+            .entries
+            .push("You search the area but find nothing.".to_string());
+    }
+    RunState::PlayerTurn
+}
+
 /// Skip turn, healing player if no monster NPCs are in the viewshed.
+///
+/// Waiting also gives the player a small chance to notice a secret door next to them.
 fn skip_turn(ecs: &mut World) -> RunState {
+    search_for_secret_doors(ecs, PASSIVE_SEARCH_CHANCE);
+
     let player_entity = ecs.fetch::<Entity>();
     let viewshed_components = ecs.read_storage::<Viewshed>();
     let monsters = ecs.read_storage::<Monster>();
@@ -170,6 +344,24 @@ pub fn try_next_level(ecs: &mut World) -> bool {
     }
 }
 
+/// Attempt to send the player to the previous level.
+///
+/// Push to message to ECS game log if player is unable.
+pub fn try_previous_level(ecs: &mut World) -> bool {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+    if map.tiles[player_idx] == TileType::UpStairs {
+        true
+    } else {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog
+            .entries
+            .push("There is no way up to the previous level from here.".to_string());
+        false
+    }
+}
+
 /// Process command to get item, checking if item exists, and telling the system the player wants to pick the item up.
 fn get_item(ecs: &mut World) {
     let player_pos = ecs.fetch::<Point>();
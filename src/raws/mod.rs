@@ -0,0 +1,38 @@
+//! Monsters, items, and traps defined in data, so new ones can be added without recompiling.
+//!
+//! Definitions are read from raws/spawns.json in the working directory when it's there, and otherwise from the copy built into the game.
+
+mod raw_structs;
+mod rawmaster;
+
+pub use rawmaster::RawMaster;
+
+use raw_structs::Raws;
+use specs::prelude::*;
+use std::sync::OnceLock;
+
+/// Raws file read at startup, relative to the working directory.
+const RAWS_PATH: &str = "./raws/spawns.json";
+
+/// Raws built into the game, for when there's no raws file beside it.
+const BUILTIN_RAWS: &str = include_str!("../../raws/spawns.json");
+
+static RAWS: OnceLock<RawMaster> = OnceLock::new();
+
+/// Loaded raws, read on first use.
+pub fn raws() -> &'static RawMaster {
+    RAWS.get_or_init(|| {
+        let (source, json) = match std::fs::read_to_string(RAWS_PATH) {
+            Ok(json) => (RAWS_PATH, json),
+            Err(_) => ("built-in raws", BUILTIN_RAWS.to_string()),
+        };
+        let raws: Raws = serde_json::from_str(&json)
+            .unwrap_or_else(|err| panic!("Unable to parse {}: {}", source, err));
+        RawMaster::new(raws)
+    })
+}
+
+/// Spawn the named monster, item, or trap at (x, y), returning it, or None if the raws don't define it.
+pub fn spawn_named_entity(ecs: &mut World, name: &str, x: i32, y: i32) -> Option<Entity> {
+    raws().spawn_named_entity(ecs, name, x, y)
+}
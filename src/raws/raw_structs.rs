@@ -0,0 +1,199 @@
+//! Shapes of the entity definitions in the raws file, as read from JSON.
+
+use crate::components::EquipmentSlot;
+use serde::Deserialize;
+
+/// Everything defined in the raws file.
+#[derive(Deserialize)]
+pub struct Raws {
+    pub items: Vec<RawItem>,
+    pub mobs: Vec<RawMob>,
+    pub traps: Vec<RawTrap>,
+    pub loot_tables: Vec<RawLootTable>,
+    pub groups: Vec<RawGroup>,
+    pub spawn_table: Vec<RawSpawnTableEntry>,
+}
+
+/// How an entity is drawn. Colors are HTML hex codes, like "#FF0000".
+#[derive(Deserialize)]
+pub struct RawRenderable {
+    /// A single character.
+    pub glyph: String,
+    pub fg: String,
+    pub bg: String,
+    /// Lower orders are drawn over higher ones.
+    pub order: i32,
+}
+
+/// Something that can be picked up. Each effect that's left out isn't added.
+#[derive(Deserialize)]
+pub struct RawItem {
+    pub name: String,
+    pub renderable: RawRenderable,
+    /// Used up when used.
+    #[serde(default)]
+    pub consumable: bool,
+    /// Targeted from up to this many tiles away.
+    pub ranged: Option<i32>,
+    pub inflicts_damage: Option<i32>,
+    /// Radius of the blast around the target.
+    pub area_of_effect: Option<i32>,
+    pub provides_healing: Option<i32>,
+    /// Number of turns the target is confused for.
+    pub confusion: Option<i32>,
+    pub equipable: Option<RawEquipable>,
+}
+
+/// Equipment slot an item goes in, and what it does while equipped.
+#[derive(Deserialize)]
+pub struct RawEquipable {
+    pub slot: EquipmentSlot,
+    pub power_bonus: Option<i32>,
+    pub defense_bonus: Option<i32>,
+}
+
+/// How a mob behaves.
+#[derive(Deserialize, Debug, PartialEq, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum RawAi {
+    /// Hunts the player.
+    Monster,
+    /// Wanders about, ignoring the player.
+    Bystander,
+    /// Stays behind the counter.
+    Vendor,
+}
+
+/// A creature, hostile or otherwise.
+#[derive(Deserialize)]
+pub struct RawMob {
+    pub name: String,
+    pub renderable: RawRenderable,
+    pub ai: RawAi,
+    #[serde(default)]
+    pub blocks_tile: bool,
+    /// Can cross deep water.
+    #[serde(default)]
+    pub swimmer: bool,
+    /// How far the mob can see. Mobs without it can't see at all.
+    pub vision_range: Option<i32>,
+    /// Mobs without stats can't fight, or be fought.
+    pub stats: Option<RawStats>,
+    /// Hit points healed every turn.
+    pub regeneration: Option<i32>,
+    /// Alerts every monster on the level the first time it sees the player.
+    #[serde(default)]
+    pub war_cry: bool,
+    /// Names of items the mob carries, and drops when it dies.
+    #[serde(default)]
+    pub carries: Vec<String>,
+    /// Loot table the mob might drop something from when it dies.
+    pub loot: Option<RawLoot>,
+    /// Makes the mob a unique boss, met at most once a run.
+    pub boss: Option<RawBoss>,
+}
+
+/// Chance of a mob dropping something from a loot table when it dies.
+#[derive(Deserialize)]
+pub struct RawLoot {
+    /// Name of a loot table in the raws.
+    pub table: String,
+    /// Percent chance of a drop on the first level.
+    pub chance: i32,
+    /// Added to the chance for every level deeper.
+    #[serde(default)]
+    pub chance_per_depth: i32,
+}
+
+/// Items a mob can drop, one of which is picked when it drops something.
+#[derive(Deserialize)]
+pub struct RawLootTable {
+    pub name: String,
+    pub drops: Vec<RawLootDrop>,
+}
+
+/// Item in a loot table, and its weight against the other drops.
+#[derive(Deserialize)]
+pub struct RawLootDrop {
+    /// Name of an item in the raws.
+    pub name: String,
+    pub weight: i32,
+}
+
+/// Where a boss is met, and what the game log says about it.
+#[derive(Deserialize)]
+pub struct RawBoss {
+    /// Depth of the level the boss waits on.
+    pub depth: i32,
+    /// Logged when the player arrives on the boss's level.
+    pub announcement: String,
+    /// Logged in place of the usual death message.
+    pub death_message: String,
+}
+
+/// Combat stats of a mob, which starts at full health.
+#[derive(Deserialize)]
+pub struct RawStats {
+    pub max_hp: i32,
+    pub defense: i32,
+    pub power: i32,
+}
+
+/// A hidden trap, set off by whatever steps on it. Each effect that's left out isn't added.
+#[derive(Deserialize)]
+pub struct RawTrap {
+    pub name: String,
+    pub renderable: RawRenderable,
+    pub inflicts_damage: Option<i32>,
+    /// Sends whatever steps on it somewhere random on the level.
+    #[serde(default)]
+    pub teleports: bool,
+    /// Alerts every monster on the level.
+    #[serde(default)]
+    pub raises_alarm: bool,
+    /// Goes off once, then stays sprung.
+    #[serde(default)]
+    pub single_activation: bool,
+}
+
+/// Mobs that spawn together, formed up on neighbouring tiles.
+#[derive(Deserialize)]
+pub struct RawGroup {
+    pub name: String,
+    /// Leaders first, since the group forms up around whoever is spawned first.
+    pub members: Vec<RawGroupMember>,
+}
+
+/// Mob in a group, and how many of it there are.
+#[derive(Deserialize)]
+pub struct RawGroupMember {
+    /// Name of a mob in the raws.
+    pub name: String,
+    pub min: i32,
+    pub max: i32,
+}
+
+/// Kind of thing a spawn table entry spawns.
+#[derive(Deserialize, Debug, PartialEq, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum RawSpawnTag {
+    Monster,
+    Item,
+    Trap,
+}
+
+/// Chance of something spawning on levels within a range of depths.
+#[derive(Deserialize)]
+pub struct RawSpawnTableEntry {
+    /// Name of a mob, item, trap, or group in the raws.
+    pub name: String,
+    /// Weight at min_depth, against the other entries.
+    pub weight: i32,
+    pub min_depth: i32,
+    /// Deepest level it spawns on. Without it, there's no limit.
+    pub max_depth: Option<i32>,
+    /// Added to the weight for every level below min_depth.
+    #[serde(default)]
+    pub weight_per_depth: i32,
+    pub tags: Vec<RawSpawnTag>,
+}
@@ -0,0 +1,523 @@
+//! Index over the loaded raws, and the generic spawner that builds entities from them.
+
+use super::raw_structs::{
+    RawAi, RawBoss, RawGroup, RawItem, RawLootTable, RawMob, RawRenderable, RawSpawnTableEntry,
+    RawSpawnTag, RawTrap, Raws,
+};
+use crate::{
+    BlocksTile, CombatStats, Monster, Name, Position, Renderable, Viewshed,
+    components::{
+        AreaOfEffect, Boss, Bystander, Confusion, Consumable, DefenseBonus, EntryTrigger,
+        Equipable, Hidden, InBackpack, InflictsDamage, Item, LootTable, MeleePowerBonus,
+        ProvidesHealing, RaisesAlarm, Ranged, Regeneration, SerializeMe, SingleActivation, Swimmer,
+        TeleportsEntity, Vendor, WarCry,
+    },
+};
+use rltk::{RGB, RandomNumberGenerator};
+use specs::{
+    prelude::*,
+    saveload::{MarkedBuilder, SimpleMarker},
+};
+use std::collections::HashMap;
+
+/// Kind of entity a name in the raws refers to, and where it is in its list.
+#[derive(Copy, Clone)]
+enum RawIndex {
+    Item(usize),
+    Mob(usize),
+    Trap(usize),
+    Group(usize),
+}
+
+/// Loaded raws, indexed by entity name.
+pub struct RawMaster {
+    raws: Raws,
+    index: HashMap<String, RawIndex>,
+    /// Position of each loot table in its list, by name.
+    loot_index: HashMap<String, usize>,
+}
+
+impl RawMaster {
+    /// Index the raws, checking every definition can be spawned.
+    ///
+    /// Panics naming the first bad definition found, so mistakes in the file are caught at startup rather than mid-game.
+    pub fn new(raws: Raws) -> RawMaster {
+        let mut index: HashMap<String, RawIndex> = HashMap::new();
+        let names = raws
+            .items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| (&item.name, &item.renderable, RawIndex::Item(i)))
+            .chain(
+                raws.mobs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, mob)| (&mob.name, &mob.renderable, RawIndex::Mob(i))),
+            )
+            .chain(
+                raws.traps
+                    .iter()
+                    .enumerate()
+                    .map(|(i, trap)| (&trap.name, &trap.renderable, RawIndex::Trap(i))),
+            );
+        for (name, renderable, raw_index) in names {
+            if let Err(reason) = check_renderable(renderable) {
+                panic!("Bad renderable for {} in raws: {}", name, reason);
+            }
+            if index.insert(name.clone(), raw_index).is_some() {
+                panic!("{} is defined more than once in raws", name);
+            }
+        }
+
+        let mut loot_index: HashMap<String, usize> = HashMap::new();
+        for (i, table) in raws.loot_tables.iter().enumerate() {
+            if let Err(reason) = check_loot_table(table, &index) {
+                panic!("Bad loot table {} in raws: {}", table.name, reason);
+            }
+            if loot_index.insert(table.name.clone(), i).is_some() {
+                panic!(
+                    "Loot table {} is defined more than once in raws",
+                    table.name
+                );
+            }
+        }
+
+        for mob in &raws.mobs {
+            if let Err(reason) = check_mob_loot(mob, &index, &loot_index) {
+                panic!("Bad mob {} in raws: {}", mob.name, reason);
+            }
+        }
+
+        for (i, group) in raws.groups.iter().enumerate() {
+            if let Err(reason) = check_group(group, &index) {
+                panic!("Bad group {} in raws: {}", group.name, reason);
+            }
+            if index
+                .insert(group.name.clone(), RawIndex::Group(i))
+                .is_some()
+            {
+                panic!("{} is defined more than once in raws", group.name);
+            }
+        }
+
+        for entry in &raws.spawn_table {
+            if let Err(reason) = check_spawn_table_entry(entry, &index, &raws.mobs) {
+                rltk::console::log(format!(
+                    "Warning: spawn table entry for {} in raws {}",
+                    entry.name, reason
+                ));
+            }
+        }
+
+        RawMaster {
+            raws,
+            index,
+            loot_index,
+        }
+    }
+
+    /// Name and weight of everything in the spawn table that can spawn at the given depth.
+    pub fn spawn_weights(&self, depth: i32) -> impl Iterator<Item = (&str, i32)> {
+        self.raws
+            .spawn_table
+            .iter()
+            .filter(move |entry| {
+                depth >= entry.min_depth && entry.max_depth.is_none_or(|max| depth <= max)
+            })
+            .map(move |entry| {
+                let weight = entry.weight + entry.weight_per_depth * (depth - entry.min_depth);
+                (entry.name.as_str(), weight)
+            })
+    }
+
+    /// Name and details of every boss that waits at the given depth.
+    pub fn bosses_at_depth(&self, depth: i32) -> impl Iterator<Item = (&str, &RawBoss)> {
+        self.raws.mobs.iter().filter_map(move |mob| {
+            mob.boss
+                .as_ref()
+                .filter(|boss| boss.depth == depth)
+                .map(|boss| (mob.name.as_str(), boss))
+        })
+    }
+
+    /// Whether the name is of a group, rather than a single mob, item, or trap.
+    pub fn is_group(&self, name: &str) -> bool {
+        matches!(self.index.get(name), Some(RawIndex::Group(_)))
+    }
+
+    /// Roll how many of each member the named group has this time, returning their names with leaders first, or None if it isn't a group.
+    pub fn roll_group(&self, name: &str, rng: &mut RandomNumberGenerator) -> Option<Vec<String>> {
+        let Some(RawIndex::Group(i)) = self.index.get(name) else {
+            return None;
+        };
+        let mut members: Vec<String> = Vec::new();
+        for member in &self.raws.groups[*i].members {
+            let count = rng.range(member.min, member.max + 1);
+            for _ in 0..count {
+                members.push(member.name.clone());
+            }
+        }
+        Some(members)
+    }
+
+    /// Spawn the named entity at (x, y), returning it, or None if the raws don't define it.
+    ///
+    /// Groups aren't single entities, so they're spawned by spawner::spawn_entity instead.
+    pub fn spawn_named_entity(
+        &self,
+        ecs: &mut World,
+        name: &str,
+        x: i32,
+        y: i32,
+    ) -> Option<Entity> {
+        let entity = match self.index.get(name)? {
+            RawIndex::Item(i) => spawn_item(ecs, &self.raws.items[*i], x, y),
+            RawIndex::Mob(i) => {
+                let mob = &self.raws.mobs[*i];
+                let entity = spawn_mob(ecs, mob, x, y);
+                for item in &mob.carries {
+                    self.spawn_in_backpack(ecs, item, entity);
+                }
+                entity
+            }
+            RawIndex::Trap(i) => spawn_trap(ecs, &self.raws.traps[*i], x, y),
+            RawIndex::Group(_) => return None,
+        };
+        Some(entity)
+    }
+
+    /// Spawn the named item straight into owner's backpack.
+    fn spawn_in_backpack(&self, ecs: &mut World, name: &str, owner: Entity) {
+        // Carried items were checked to be items when the raws were indexed.
+        let Some(RawIndex::Item(i)) = self.index.get(name) else {
+            return;
+        };
+        let item = spawn_item(ecs, &self.raws.items[*i], 0, 0);
+        ecs.write_storage::<Position>().remove(item);
+        ecs.write_storage::<InBackpack>()
+            .insert(item, InBackpack { owner })
+            .expect("Unable to insert backpack entry");
+    }
+
+    /// Roll the named loot table for a drop, returning the item's name, or None if there's no such table.
+    pub fn roll_loot(&self, table: &str, rng: &mut RandomNumberGenerator) -> Option<&str> {
+        let drops = &self.raws.loot_tables[*self.loot_index.get(table)?].drops;
+        let total_weight: i32 = drops.iter().map(|drop| drop.weight).sum();
+        let mut roll = rng.range(0, total_weight);
+        for drop in drops {
+            if roll < drop.weight {
+                return Some(&drop.name);
+            }
+            roll -= drop.weight;
+        }
+        None
+    }
+}
+
+/// Check a renderable has a single glyph and colors that parse.
+fn check_renderable(renderable: &RawRenderable) -> Result<(), String> {
+    if renderable.glyph.chars().count() != 1 {
+        return Err(format!(
+            "glyph '{}' isn't a single character",
+            renderable.glyph
+        ));
+    }
+    for color in [&renderable.fg, &renderable.bg] {
+        if RGB::from_hex(color).is_err() {
+            return Err(format!("color '{}' isn't a hex code like #FF0000", color));
+        }
+    }
+    Ok(())
+}
+
+/// Check every drop in a loot table is an item in the raws, with a weight that gives it a chance.
+fn check_loot_table(table: &RawLootTable, index: &HashMap<String, RawIndex>) -> Result<(), String> {
+    if table.drops.is_empty() {
+        return Err("has no drops".to_string());
+    }
+    for drop in &table.drops {
+        if !matches!(index.get(&drop.name), Some(RawIndex::Item(_))) {
+            return Err(format!("drop {} isn't an item", drop.name));
+        }
+        if drop.weight < 1 {
+            return Err(format!(
+                "drop {} has weight {}, below 1",
+                drop.name, drop.weight
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check everything a mob carries is an item in the raws, and its loot table is defined.
+fn check_mob_loot(
+    mob: &RawMob,
+    index: &HashMap<String, RawIndex>,
+    loot_index: &HashMap<String, usize>,
+) -> Result<(), String> {
+    if let Some(item) = mob
+        .carries
+        .iter()
+        .find(|item| !matches!(index.get(*item), Some(RawIndex::Item(_))))
+    {
+        return Err(format!("carried {} isn't an item", item));
+    }
+    if let Some(loot) = &mob.loot
+        && !loot_index.contains_key(&loot.table)
+    {
+        return Err(format!("loot table {} isn't defined", loot.table));
+    }
+    Ok(())
+}
+
+/// Check every member of a group is a mob in the raws, in numbers that make sense.
+fn check_group(group: &RawGroup, index: &HashMap<String, RawIndex>) -> Result<(), String> {
+    for member in &group.members {
+        if !matches!(index.get(&member.name), Some(RawIndex::Mob(_))) {
+            return Err(format!("member {} isn't a mob", member.name));
+        }
+        if member.min < 0 || member.max < member.min {
+            return Err(format!(
+                "member {} has min {} and max {}, which aren't a range of counts",
+                member.name, member.min, member.max
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check a spawn table entry names something defined in the raws that isn't a boss, and is tagged with what it is.
+fn check_spawn_table_entry(
+    entry: &RawSpawnTableEntry,
+    index: &HashMap<String, RawIndex>,
+    mobs: &[RawMob],
+) -> Result<(), String> {
+    let (tag, tag_name) = match index.get(&entry.name) {
+        None => return Err("has no matching definition, so it will never spawn".to_string()),
+        Some(RawIndex::Item(_)) => (RawSpawnTag::Item, "item"),
+        Some(RawIndex::Mob(_)) => (RawSpawnTag::Monster, "monster"),
+        Some(RawIndex::Trap(_)) => (RawSpawnTag::Trap, "trap"),
+        Some(RawIndex::Group(_)) => (RawSpawnTag::Monster, "monster"),
+    };
+    if let Some(RawIndex::Mob(i)) = index.get(&entry.name)
+        && mobs[*i].boss.is_some()
+    {
+        return Err("is a boss, which only spawns on its own level".to_string());
+    }
+    if !entry.tags.contains(&tag) {
+        return Err(format!("is missing the {} tag", tag_name));
+    }
+    if let Some(max_depth) = entry.max_depth
+        && max_depth < entry.min_depth
+    {
+        return Err(format!(
+            "has max_depth {} less than min_depth {}, so it will never spawn",
+            max_depth, entry.min_depth
+        ));
+    }
+    Ok(())
+}
+
+/// Parts every spawned entity has: where it is, how it's drawn, and its name.
+fn base_entity<'a>(
+    ecs: &'a mut World,
+    name: &str,
+    renderable: &RawRenderable,
+    x: i32,
+    y: i32,
+) -> EntityBuilder<'a> {
+    // Renderables were checked when the raws were indexed.
+    let glyph = renderable.glyph.chars().next().unwrap_or('?');
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(glyph),
+            fg: RGB::from_hex(&renderable.fg).unwrap_or_else(|_| RGB::named(rltk::WHITE)),
+            bg: RGB::from_hex(&renderable.bg).unwrap_or_else(|_| RGB::named(rltk::BLACK)),
+            render_order: renderable.order,
+        })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+}
+
+fn spawn_item(ecs: &mut World, item: &RawItem, x: i32, y: i32) -> Entity {
+    let mut builder = base_entity(ecs, &item.name, &item.renderable, x, y).with(Item {});
+    if item.consumable {
+        builder = builder.with(Consumable {});
+    }
+    if let Some(range) = item.ranged {
+        builder = builder.with(Ranged { range });
+    }
+    if let Some(damage) = item.inflicts_damage {
+        builder = builder.with(InflictsDamage { damage });
+    }
+    if let Some(radius) = item.area_of_effect {
+        builder = builder.with(AreaOfEffect { radius });
+    }
+    if let Some(heal_amount) = item.provides_healing {
+        builder = builder.with(ProvidesHealing { heal_amount });
+    }
+    if let Some(turns) = item.confusion {
+        builder = builder.with(Confusion { turns });
+    }
+    if let Some(equipable) = &item.equipable {
+        builder = builder.with(Equipable {
+            slot: equipable.slot,
+        });
+        if let Some(power) = equipable.power_bonus {
+            builder = builder.with(MeleePowerBonus { power });
+        }
+        if let Some(defense) = equipable.defense_bonus {
+            builder = builder.with(DefenseBonus { defense });
+        }
+    }
+    builder.build()
+}
+
+fn spawn_mob(ecs: &mut World, mob: &RawMob, x: i32, y: i32) -> Entity {
+    let mut builder = base_entity(ecs, &mob.name, &mob.renderable, x, y);
+    builder = match mob.ai {
+        RawAi::Monster => builder.with(Monster {}),
+        RawAi::Bystander => builder.with(Bystander {}),
+        RawAi::Vendor => builder.with(Vendor {}),
+    };
+    if mob.blocks_tile {
+        builder = builder.with(BlocksTile {});
+    }
+    if mob.swimmer {
+        builder = builder.with(Swimmer {});
+    }
+    if let Some(range) = mob.vision_range {
+        builder = builder.with(Viewshed {
+            visible_tiles: Vec::new(),
+            range,
+            dirty: true,
+        });
+    }
+    if let Some(stats) = &mob.stats {
+        builder = builder.with(CombatStats {
+            max_hp: stats.max_hp,
+            hp: stats.max_hp,
+            defense: stats.defense,
+            power: stats.power,
+        });
+    }
+    if let Some(amount) = mob.regeneration {
+        builder = builder.with(Regeneration { amount });
+    }
+    if mob.war_cry {
+        builder = builder.with(WarCry {});
+    }
+    if let Some(loot) = &mob.loot {
+        builder = builder.with(LootTable {
+            table: loot.table.clone(),
+            chance: loot.chance,
+            chance_per_depth: loot.chance_per_depth,
+        });
+    }
+    if let Some(boss) = &mob.boss {
+        builder = builder.with(Boss {
+            death_message: boss.death_message.clone(),
+        });
+    }
+    builder.build()
+}
+
+fn spawn_trap(ecs: &mut World, trap: &RawTrap, x: i32, y: i32) -> Entity {
+    let mut builder = base_entity(ecs, &trap.name, &trap.renderable, x, y)
+        .with(Hidden {})
+        .with(EntryTrigger {});
+    if let Some(damage) = trap.inflicts_damage {
+        builder = builder.with(InflictsDamage { damage });
+    }
+    if trap.teleports {
+        builder = builder.with(TeleportsEntity {});
+    }
+    if trap.raises_alarm {
+        builder = builder.with(RaisesAlarm {});
+    }
+    if trap.single_activation {
+        builder = builder.with(SingleActivation {});
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raws::BUILTIN_RAWS;
+
+    /// Raws with a potion that spawns from the top and gets commoner, and a scroll that only spawns on levels 3 to 5.
+    fn depth_raws() -> RawMaster {
+        let json = r##"{
+            "items": [
+                {
+                    "name": "Potion",
+                    "renderable": { "glyph": "!", "fg": "#FF00FF", "bg": "#000000", "order": 2 }
+                },
+                {
+                    "name": "Scroll",
+                    "renderable": { "glyph": "?", "fg": "#00FFFF", "bg": "#000000", "order": 2 }
+                }
+            ],
+            "mobs": [],
+            "traps": [],
+            "loot_tables": [],
+            "groups": [],
+            "spawn_table": [
+                { "name": "Potion", "weight": 10, "min_depth": 1, "weight_per_depth": 2, "tags": ["item"] },
+                { "name": "Scroll", "weight": 4, "min_depth": 3, "max_depth": 5, "tags": ["item"] }
+            ]
+        }"##;
+        RawMaster::new(serde_json::from_str(json).expect("Unable to parse test raws"))
+    }
+
+    /// Raws as shipped with the game.
+    fn shipped_raws() -> RawMaster {
+        RawMaster::new(serde_json::from_str(BUILTIN_RAWS).expect("Unable to parse shipped raws"))
+    }
+
+    #[test]
+    fn shipped_raws_load_without_warnings() {
+        let master = shipped_raws();
+        for entry in &master.raws.spawn_table {
+            if let Err(reason) = check_spawn_table_entry(entry, &master.index, &master.raws.mobs) {
+                panic!("Spawn table entry for {} in raws {}", entry.name, reason);
+            }
+        }
+    }
+
+    #[test]
+    fn spawn_weights_follow_depth() {
+        let master = depth_raws();
+        let weights = |depth| master.spawn_weights(depth).collect::<Vec<_>>();
+
+        assert_eq!(weights(1), vec![("Potion", 10)]);
+        assert_eq!(weights(2), vec![("Potion", 12)]);
+        assert_eq!(weights(3), vec![("Potion", 14), ("Scroll", 4)]);
+        assert_eq!(weights(5), vec![("Potion", 18), ("Scroll", 4)]);
+        assert_eq!(weights(6), vec![("Potion", 20)]);
+    }
+
+    #[test]
+    fn loot_comes_from_the_table() {
+        let master = shipped_raws();
+        let table = &master.raws.loot_tables[master.loot_index["Goblin Loot"]];
+        let mut rng = RandomNumberGenerator::seeded(7);
+        for _ in 0..200 {
+            let drop = master
+                .roll_loot("Goblin Loot", &mut rng)
+                .expect("Goblin Loot should always drop something");
+            assert!(table.drops.iter().any(|entry| entry.name == drop));
+        }
+    }
+
+    #[test]
+    fn unknown_loot_table_drops_nothing() {
+        let master = shipped_raws();
+        let mut rng = RandomNumberGenerator::seeded(7);
+        assert_eq!(master.roll_loot("Dragon Hoard", &mut rng), None);
+    }
+}
@@ -0,0 +1,31 @@
+//! Logic for entities that heal on their own.
+
+use crate::{CombatStats, RunState, components::Regeneration};
+use specs::prelude::*;
+
+/// System for healing regenerating entities a little every turn in an ECS.
+pub struct RegenerationSystem {}
+
+impl<'a> System<'a> for RegenerationSystem {
+    type SystemData = (
+        ReadExpect<'a, RunState>,
+        ReadStorage<'a, Regeneration>,
+        WriteStorage<'a, CombatStats>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (runstate, regenerations, mut stats) = data;
+
+        // Only run once a turn, on the monsters' turn.
+        if *runstate != RunState::MonsterTurn {
+            return;
+        }
+
+        for (regeneration, stats) in (&regenerations, &mut stats).join() {
+            // The dead stay dead.
+            if stats.hp > 0 {
+                stats.hp = i32::min(stats.max_hp, stats.hp + regeneration.amount);
+            }
+        }
+    }
+}
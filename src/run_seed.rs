@@ -0,0 +1,102 @@
+//! Run seed that every random roll in a run is derived from, so a run can be replayed exactly.
+
+use rltk::RandomNumberGenerator;
+
+/// Random seeds are kept to at most this many digits so they're easy to read back and type in.
+const RANDOM_SEED_LIMIT: u64 = 1_000_000_000;
+
+/// Resource holding the seed for the current run.
+#[derive(PartialEq, Copy, Clone)]
+pub struct RunSeed {
+    pub seed: u64,
+}
+
+impl RunSeed {
+    /// Pick a new seed at random.
+    pub fn random() -> RunSeed {
+        let mut rng = RandomNumberGenerator::new();
+        RunSeed {
+            seed: rng.next_u64() % RANDOM_SEED_LIMIT,
+        }
+    }
+
+    /// Seed passed on the command line as `--seed <number>`, if any.
+    pub fn from_args() -> Option<RunSeed> {
+        let args: Vec<String> = std::env::args().collect();
+        let value = args
+            .iter()
+            .position(|arg| arg == "--seed")
+            .and_then(|i| args.get(i + 1))?;
+
+        match value.parse::<u64>() {
+            Ok(seed) => Some(RunSeed { seed }),
+            Err(_) => {
+                rltk::console::log(format!("Ignoring invalid seed '{}'", value));
+                None
+            }
+        }
+    }
+
+    /// Generator for gameplay rolls, such as combat, from the start of the run.
+    pub fn gameplay_rng(&self) -> RandomNumberGenerator {
+        RandomNumberGenerator::seeded(self.seed)
+    }
+
+    /// Generator for building the level at the given depth.
+    ///
+    /// Each depth gets its own generator, so a level's layout and spawns don't depend on anything the player did before reaching it.
+    pub fn level_rng(&self, depth: i32) -> RandomNumberGenerator {
+        // Spread depths far apart so neighboring seeds don't build neighboring levels.
+        let level_seed = self.seed ^ (depth as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        RandomNumberGenerator::seeded(level_seed)
+    }
+}
+
+/// Move the gameplay generator on to a fresh seed drawn from it, returning that seed.
+///
+/// A generator's position can't be read back out, so saving reseeds it instead: the run then carries on from the returned seed whether or not the save is loaded.
+pub fn reseed_gameplay_rng(rng: &mut RandomNumberGenerator) -> u64 {
+    let seed = rng.next_u64();
+    *rng = RandomNumberGenerator::seeded(seed);
+    seed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_builders;
+
+    #[test]
+    fn same_seed_and_depth_build_the_same_level() {
+        let run_seed = RunSeed { seed: 42 };
+        for depth in 1..=6 {
+            let first = map_builders::build_level(depth, &mut run_seed.level_rng(depth));
+            let second = map_builders::build_level(depth, &mut run_seed.level_rng(depth));
+            assert!(first.get_map().tiles == second.get_map().tiles);
+            assert_eq!(first.get_spawn_list(), second.get_spawn_list());
+        }
+    }
+
+    #[test]
+    fn each_depth_gets_its_own_generator() {
+        let run_seed = RunSeed { seed: 42 };
+        let rolls: Vec<u64> = (1..=10)
+            .map(|depth| run_seed.level_rng(depth).next_u64())
+            .collect();
+        for (i, roll) in rolls.iter().enumerate() {
+            assert!(!rolls[i + 1..].contains(roll), "depth {} repeats", i + 1);
+        }
+    }
+
+    #[test]
+    fn reseeding_carries_on_with_the_same_rolls() {
+        let mut rng = RunSeed { seed: 42 }.gameplay_rng();
+        rng.roll_dice(3, 6);
+        let seed = reseed_gameplay_rng(&mut rng);
+
+        let mut reloaded = RandomNumberGenerator::seeded(seed);
+        for _ in 0..20 {
+            assert_eq!(rng.roll_dice(1, 100), reloaded.roll_dice(1, 100));
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::components::*;
+use serde::{Deserialize, Serialize};
 use specs::error::NoError;
 use specs::prelude::*;
 use specs::saveload::{
@@ -8,6 +9,15 @@ use std::fs;
 use std::fs::File;
 use std::path::Path;
 
+/// Version of the save format, written at the start of every save. Bump it whenever the format changes.
+const SAVE_VERSION: u32 = 1;
+
+/// First thing in a save, so saves in another format can be turned away before anything is loaded.
+#[derive(Serialize, Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
 /// Work around failing to compile if has >16 component types.
 macro_rules! serialize_individually {
     ($ecs:expr, $ser:expr, $data:expr, $( $type:ty),*) => {
@@ -44,10 +54,30 @@ macro_rules! deserialize_individually {
 pub fn save_game(ecs: &mut specs::World) {
     // Create helper
     let mapcopy = ecs.get_mut::<crate::map::Map>().unwrap().clone(); // Deep copy for serialization.
+    let dungeon = ecs
+        .get_mut::<crate::dungeon::MasterDungeonMap>()
+        .unwrap()
+        .clone();
+    let seed = ecs.fetch::<crate::run_seed::RunSeed>().seed;
+    let gameplay_seed = crate::run_seed::reseed_gameplay_rng(
+        &mut ecs.write_resource::<rltk::RandomNumberGenerator>(),
+    );
+    let bosses = ecs
+        .get_mut::<crate::bosses::SpawnedBosses>()
+        .unwrap()
+        .clone();
+    let group_ids = ecs.get_mut::<crate::spawner::GroupIds>().unwrap().clone();
     // Creates helper, creating entities holding deep copy of map to be serialized on save.
     let savehelper = ecs
         .create_entity()
-        .with(SerializationHelper { map: mapcopy })
+        .with(SerializationHelper {
+            map: mapcopy,
+            dungeon,
+            seed,
+            gameplay_seed,
+            bosses,
+            group_ids,
+        })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
 
@@ -61,6 +91,11 @@ pub fn save_game(ecs: &mut specs::World) {
 
         let writer = File::create("./savegame.json").unwrap();
         let mut serializer = serde_json::Serializer::new(writer);
+        SaveHeader {
+            version: SAVE_VERSION,
+        }
+        .serialize(&mut serializer)
+        .unwrap();
         serialize_individually!(
             ecs,
             serializer,
@@ -91,7 +126,24 @@ pub fn save_game(ecs: &mut specs::World) {
             Equipped,
             MeleePowerBonus,
             DefenseBonus,
-            WantsToRemoveItem
+            WantsToRemoveItem,
+            OtherLevelPosition,
+            Hidden,
+            EntryTrigger,
+            EntityMoved,
+            SingleActivation,
+            TeleportsEntity,
+            RaisesAlarm,
+            Alerted,
+            Swimmer,
+            Slowed,
+            Bystander,
+            Vendor,
+            GroupMember,
+            Boss,
+            Regeneration,
+            WarCry,
+            LootTable
         );
     }
 
@@ -109,7 +161,26 @@ pub fn does_save_exist() -> bool {
 }
 
 /// Load game data from disk.
-pub fn load_game(ecs: &mut World) {
+///
+/// Saves in another format are left alone, and the reason they can't be loaded is returned, before anything in the ecs is touched.
+pub fn load_game(ecs: &mut World) -> Result<(), String> {
+    let data = fs::read_to_string("./savegame.json").map_err(|err| err.to_string())?;
+    let mut de = serde_json::Deserializer::from_str(&data);
+    match SaveHeader::deserialize(&mut de) {
+        Ok(header) if header.version == SAVE_VERSION => {}
+        Ok(header) => {
+            return Err(format!(
+                "The save is from version {} of the save format, but only version {} can be loaded",
+                header.version, SAVE_VERSION
+            ));
+        }
+        Err(_) => {
+            return Err(
+                "The save is from an older version of the game and can't be loaded".to_string(),
+            );
+        }
+    }
+
     // Scope to keep borrow check happy because ecs is mut.
     {
         // Delete everything
@@ -123,9 +194,6 @@ pub fn load_game(ecs: &mut World) {
         }
     }
 
-    let data = fs::read_to_string("./savegame.json").unwrap();
-    let mut de = serde_json::Deserializer::from_str(&data);
-
     // Another scope to keep borrow checker happy because ecs is mut.
     {
         let mut d = (
@@ -164,7 +232,24 @@ pub fn load_game(ecs: &mut World) {
             Equipped,
             MeleePowerBonus,
             DefenseBonus,
-            WantsToRemoveItem
+            WantsToRemoveItem,
+            OtherLevelPosition,
+            Hidden,
+            EntryTrigger,
+            EntityMoved,
+            SingleActivation,
+            TeleportsEntity,
+            RaisesAlarm,
+            Alerted,
+            Swimmer,
+            Slowed,
+            Bystander,
+            Vendor,
+            GroupMember,
+            Boss,
+            Regeneration,
+            WarCry,
+            LootTable
         );
     }
 
@@ -183,7 +268,15 @@ pub fn load_game(ecs: &mut World) {
             // Replace existing map with deep copy of saved map.
             *worldmap = h.map.clone();
             // Need to create empty vectors for tile_content because it doesn't get serialized/saved.
-            worldmap.tile_content = vec![Vec::new(); crate::map::MAPCOUNT];
+            worldmap.tile_content = vec![Vec::new(); (worldmap.width * worldmap.height) as usize];
+            *ecs.write_resource::<crate::dungeon::MasterDungeonMap>() = h.dungeon.clone();
+            *ecs.write_resource::<crate::bosses::SpawnedBosses>() = h.bosses.clone();
+            *ecs.write_resource::<crate::spawner::GroupIds>() = h.group_ids.clone();
+            // Restore the run seed, and carry on gameplay rolls from where the save left them.
+            *ecs.write_resource::<crate::run_seed::RunSeed>() =
+                crate::run_seed::RunSeed { seed: h.seed };
+            *ecs.write_resource::<rltk::RandomNumberGenerator>() =
+                rltk::RandomNumberGenerator::seeded(h.gameplay_seed);
             deleteme = Some(e);
         }
 
@@ -198,6 +291,7 @@ pub fn load_game(ecs: &mut World) {
     // Delete temporary delete helper entity.
     ecs.delete_entity(deleteme.unwrap())
         .expect("Unable to delete helper");
+    Ok(())
 }
 
 /// Delete save file.
@@ -1,158 +1,191 @@
 //! Module for logic to spawn players, NPCs, items.
 
-use std::collections::HashMap;
-
 use crate::{
-    BlocksTile, CombatStats, Monster, Name, Player, Position, Renderable, Viewshed,
-    components::{
-        AreaOfEffect, Confusion, Consumable, DefenseBonus, Equipable, EquipmentSlot,
-        InflictsDamage, Item, MeleePowerBonus, ProvidesHealing, Ranged, SerializeMe,
-    },
-    map::MAPWIDTH,
+    CombatStats, Map, Name, Player, Position, Renderable, TileType, Viewshed,
+    components::{GroupMember, SerializeMe},
+    level_theme::LevelTheme,
     random_table::RandomTable,
+    raws,
     rect::Rect,
 };
 use rltk::{RGB, RandomNumberGenerator};
+use serde::{Deserialize, Serialize};
 use specs::{
     prelude::*,
     saveload::{MarkedBuilder, SimpleMarker},
 };
+use std::collections::{HashSet, VecDeque};
 
 const MAX_MONSTERS: i32 = 4;
 
-fn longsword(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('/'),
-            fg: RGB::named(rltk::YELLOW),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Longsword".to_string(),
-        })
-        .with(Item {})
-        .with(Equipable {
-            slot: EquipmentSlot::Melee,
-        })
-        .with(MeleePowerBonus { power: 4 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
+/// Furthest, in steps, a group member can be placed from where the group was rolled.
+const MAX_FORMATION_DISTANCE: i32 = 3;
 
-fn tower_shield(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('('),
-            fg: RGB::named(rltk::YELLOW),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Tower Shield".to_string(),
-        })
-        .with(Item {})
-        .with(Equipable {
-            slot: EquipmentSlot::Shield,
-        })
-        .with(DefenseBonus { defense: 3 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+/// Resource handing out a new id to every group spawned this run, so no two groups share one.
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct GroupIds {
+    next: usize,
 }
 
-fn dagger(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('/'),
-            fg: RGB::named(rltk::CYAN),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Dagger".to_string(),
-        })
-        .with(Item {})
-        .with(Equipable {
-            slot: EquipmentSlot::Melee,
-        })
-        .with(MeleePowerBonus { power: 2 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
+impl GroupIds {
+    pub fn new() -> GroupIds {
+        GroupIds { next: 0 }
+    }
 
-fn shield(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('('),
-            fg: RGB::named(rltk::CYAN),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Shield".to_string(),
-        })
-        .with(Item {})
-        .with(Equipable {
-            slot: EquipmentSlot::Shield,
-        })
-        .with(DefenseBonus { defense: 1 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+    /// Take the next unused group id.
+    fn take(&mut self) -> usize {
+        let id = self.next;
+        self.next += 1;
+        id
+    }
 }
 
 /// Fills a room with pseudo randomly placed and choosen stuff. Both NPCs and items.
-#[allow(clippy::map_entry)] // Check hashmap for membership and then insert into it.
-pub fn spawn_room(ecs: &mut World, room: &Rect, map_depth: i32) {
-    let spawn_table = room_table(map_depth);
-    let mut spawn_points: HashMap<usize, String> = HashMap::new();
-
-    // Scope prevents problems with borrow checker, which otherwise doesn't this mutable access to rng while also passing around ecs. Scope solves this because rng access gets dropped outside the scope where it's no longer needed.
-    {
-        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        let num_spawns = rng.roll_dice(1, MAX_MONSTERS + 3) + (map_depth - 1) - 3;
-
-        // Keep adding monsters and then items at random, unoccupied positions until quotas filled.
-        for _i in 0..num_spawns {
-            let mut added = false;
-            let mut tries = 0;
-            // Try to find random pont in room to spawn or give up after 20 attempts.
-            while !added && tries < 20 {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-                let idx = (y * MAPWIDTH) + x;
-                if !spawn_points.contains_key(&idx) {
-                    spawn_points.insert(idx, spawn_table.roll(&mut rng));
-                    added = true;
-                } else {
-                    tries += 1;
-                }
+///
+/// Spawns are pushed onto spawn_list as (map tile index, spawn name) so map builders can adjust them before anything is created in the ECS.
+pub fn spawn_room(
+    map: &Map,
+    rng: &mut RandomNumberGenerator,
+    room: &Rect,
+    map_depth: i32,
+    spawn_list: &mut Vec<(usize, String)>,
+) {
+    let mut possible_targets: Vec<usize> = Vec::new();
+    for y in room.y1 + 1..=room.y2 {
+        for x in room.x1 + 1..=room.x2 {
+            let idx = map.xy_idx(x, y);
+            if map.tiles[idx] == TileType::Floor {
+                possible_targets.push(idx);
             }
         }
     }
 
-    // Spawn monsters and things.
-    for spawn in spawn_points.iter() {
-        let x = (*spawn.0 % MAPWIDTH) as i32;
-        let y = (*spawn.0 / MAPWIDTH) as i32;
-
-        match spawn.1.as_ref() {
-            "Goblin" => goblin(ecs, x, y),
-            "Orc" => orc(ecs, x, y),
-            "Health Potion" => health_potion(ecs, x, y),
-            "Fireball Scroll" => fireball_scroll(ecs, x, y),
-            "Confusion Scroll" => confusion_scroll(ecs, x, y),
-            "Magic Missile Scroll" => magic_missile_scroll(ecs, x, y),
-            "Dagger" => dagger(ecs, x, y),
-            "Shield" => shield(ecs, x, y),
-            "Longsword" => longsword(ecs, x, y),
-            "Tower Shield" => tower_shield(ecs, x, y),
-            _ => {}
+    spawn_region(rng, &possible_targets, map_depth, spawn_list);
+}
+
+/// Fills an arbitrary region of map tile indices with pseudo randomly placed and choosen stuff. Both NPCs and items.
+///
+/// Used by maps that have no rooms, e.g. caves.
+pub fn spawn_region(
+    rng: &mut RandomNumberGenerator,
+    area: &[usize],
+    map_depth: i32,
+    spawn_list: &mut Vec<(usize, String)>,
+) {
+    let spawn_table = room_table(map_depth);
+    let mut areas: Vec<usize> = Vec::from(area);
+
+    let num_spawns = i32::min(
+        areas.len() as i32,
+        rng.roll_dice(1, MAX_MONSTERS + 3) + (map_depth - 1) - 3,
+    );
+
+    // Keep adding monsters and then items at random, unoccupied positions until quotas filled.
+    // Picked tiles are removed from the pool so every spawn lands on its own tile.
+    for _i in 0..num_spawns {
+        let array_index = (rng.roll_dice(1, areas.len() as i32) - 1) as usize;
+        let map_idx = areas[array_index];
+        spawn_list.push((map_idx, spawn_table.roll(rng)));
+        areas.remove(array_index);
+    }
+}
+
+/// Spawn a named monster, item, trap, or group from the raws at a map tile index.
+///
+/// Group sizes are rolled with rng, the generator the level was built with, so spawns stay fixed by the seed and depth.
+pub fn spawn_entity(ecs: &mut World, rng: &mut RandomNumberGenerator, spawn: &(&usize, &String)) {
+    let map_width = ecs.fetch::<Map>().width;
+    let x = *spawn.0 as i32 % map_width;
+    let y = *spawn.0 as i32 / map_width;
+
+    if let Some(members) = raws::raws().roll_group(spawn.1, rng) {
+        spawn_group(ecs, spawn.1, *spawn.0, &members);
+        return;
+    }
+
+    // "None" is a spot the spawn table deliberately left empty.
+    if spawn.1 != "None" && raws::spawn_named_entity(ecs, spawn.1, x, y).is_none() {
+        rltk::console::log(format!(
+            "Warning: nothing in the raws is named {}, so it wasn't spawned",
+            spawn.1
+        ));
+    }
+}
+
+/// Spawn the members of a group on free floor around a map tile index, marking each as one of the group.
+///
+/// Members that don't fit within MAX_FORMATION_DISTANCE of the tile are left out.
+fn spawn_group(ecs: &mut World, group: &str, idx: usize, members: &[String]) {
+    let map_width = ecs.fetch::<Map>().width;
+    let tiles = free_tiles_near(ecs, idx, members.len());
+
+    let mut spawned: Vec<Entity> = Vec::new();
+    for (name, tile_idx) in members.iter().zip(tiles) {
+        let x = tile_idx as i32 % map_width;
+        let y = tile_idx as i32 / map_width;
+        if let Some(entity) = raws::spawn_named_entity(ecs, name, x, y) {
+            spawned.push(entity);
+        }
+    }
+
+    let id = ecs.write_resource::<GroupIds>().take();
+    let mut group_members = ecs.write_storage::<GroupMember>();
+    for entity in spawned {
+        group_members
+            .insert(
+                entity,
+                GroupMember {
+                    group: group.to_string(),
+                    id,
+                },
+            )
+            .expect("Unable to insert group member");
+    }
+}
+
+/// Up to count floor tiles on the current map with nothing on them, nearest the tile at idx first and no more than MAX_FORMATION_DISTANCE steps from it.
+pub fn free_tiles_near(ecs: &World, idx: usize, count: usize) -> Vec<usize> {
+    let map = ecs.fetch::<Map>();
+    let positions = ecs.read_storage::<Position>();
+    let occupied: HashSet<usize> = positions
+        .join()
+        .map(|pos| map.xy_idx(pos.x, pos.y))
+        .collect();
+    formation_tiles(&map, idx, count, &occupied)
+}
+
+/// Up to count unoccupied floor tiles, nearest the tile at idx first, for a group to form up on.
+fn formation_tiles(map: &Map, idx: usize, count: usize, occupied: &HashSet<usize>) -> Vec<usize> {
+    let mut tiles: Vec<usize> = Vec::new();
+    let mut visited: HashSet<usize> = HashSet::from([idx]);
+    let mut open_list: VecDeque<(usize, i32)> = VecDeque::from([(idx, 0)]);
+
+    while let Some((tile_idx, distance)) = open_list.pop_front() {
+        if map.tiles[tile_idx] == TileType::Floor && !occupied.contains(&tile_idx) {
+            tiles.push(tile_idx);
+            if tiles.len() == count {
+                break;
+            }
+        }
+        if distance == MAX_FORMATION_DISTANCE {
+            continue;
+        }
+
+        let x = tile_idx as i32 % map.width;
+        let y = tile_idx as i32 / map.width;
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let (next_x, next_y) = (x + dx, y + dy);
+            if next_x < 1 || next_x > map.width - 2 || next_y < 1 || next_y > map.height - 2 {
+                continue;
+            }
+            let next_idx = map.xy_idx(next_x, next_y);
+            if !map.tiles[next_idx].blocks_movement() && visited.insert(next_idx) {
+                open_list.push_back((next_idx, distance + 1));
+            }
         }
     }
+
+    tiles
 }
 
 /// Spawn the player and return their entity object.
@@ -187,133 +220,86 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
         .build()
 }
 
-fn orc(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('o'), "Orc");
+/// Spawn table for a level, from the raws, with weights scaled to suit the level's theme.
+fn room_table(map_depth: i32) -> RandomTable {
+    let theme = LevelTheme::for_depth(map_depth);
+    raws::raws()
+        .spawn_weights(map_depth)
+        .fold(RandomTable::new(), |table, (name, weight)| {
+            table.add(name, theme.spawn_weight(name, weight))
+        })
 }
 
-fn goblin(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('g'), "Goblin");
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: rltk::FontCharType, name: S) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph,
-            fg: RGB::named(rltk::RED),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 1,
-        })
-        .with(Viewshed {
-            visible_tiles: Vec::new(),
-            range: 8,
-            dirty: true,
-        })
-        .with(Monster {})
-        .with(Name {
-            name: name.to_string(),
-        })
-        .with(BlocksTile {})
-        .with(CombatStats {
-            max_hp: 16,
-            hp: 16,
-            defense: 1,
-            power: 4,
-        })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
+    /// A 12x12 level of open floor inside a wall.
+    fn open_map() -> Map {
+        let mut map = Map::new(1, 12, 12);
+        for y in 1..11 {
+            for x in 1..11 {
+                let idx = map.xy_idx(x, y);
+                map.tiles[idx] = TileType::Floor;
+            }
+        }
+        map
+    }
 
-fn health_potion(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('¡'),
-            fg: RGB::named(rltk::MAGENTA),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Health Potion".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(ProvidesHealing { heal_amount: 8 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
+    fn distance(map: &Map, a: usize, b: usize) -> i32 {
+        let (ax, ay) = (a as i32 % map.width, a as i32 / map.width);
+        let (bx, by) = (b as i32 % map.width, b as i32 / map.width);
+        i32::abs(ax - bx) + i32::abs(ay - by)
+    }
 
-fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437(')'),
-            fg: RGB::named(rltk::CYAN),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Magic Missile Scroll".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 8 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
+    #[test]
+    fn members_land_on_distinct_free_floor() {
+        let mut map = open_map();
+        let idx = map.xy_idx(5, 5);
+        let stairs_idx = map.xy_idx(6, 5);
+        map.tiles[stairs_idx] = TileType::DownStairs;
+        let occupied = HashSet::from([idx, map.xy_idx(4, 5), map.xy_idx(5, 6)]);
 
-fn fireball_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437(')'),
-            fg: RGB::named(rltk::ORANGE),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Fireball Scroll".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 20 })
-        .with(AreaOfEffect { radius: 3 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
+        let tiles = formation_tiles(&map, idx, 6, &occupied);
 
-fn confusion_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437(')'),
-            fg: RGB::named(rltk::PINK),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Confusion Scroll".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(Ranged { range: 6 })
-        .with(Confusion { turns: 4 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles.iter().collect::<HashSet<_>>().len(), tiles.len());
+        for tile in tiles {
+            assert!(map.tiles[tile] == TileType::Floor);
+            assert!(!occupied.contains(&tile));
+        }
+    }
 
-fn room_table(map_depth: i32) -> RandomTable {
-    RandomTable::new()
-        .add("Goblin", 10)
-        .add("Orc", 1 + map_depth)
-        .add("Health Potion", 7)
-        .add("Fireball Scroll", 2 + map_depth)
-        .add("Confusion Scroll", 2 + map_depth)
-        .add("Magic Missile Scroll", 4)
-        .add("Dagger", 3)
-        .add("Shield", 3)
-        .add("Longsword", map_depth - 1)
-        .add("Tower Shield", map_depth - 1)
+    #[test]
+    fn members_stay_within_formation_distance() {
+        let map = open_map();
+        let idx = map.xy_idx(5, 5);
+
+        let tiles = formation_tiles(&map, idx, 100, &HashSet::new());
+
+        // Every tile within three steps of the middle of the room: 1 + 4 + 8 + 12.
+        assert_eq!(tiles.len(), 25);
+        for tile in tiles {
+            assert!(distance(&map, idx, tile) <= MAX_FORMATION_DISTANCE);
+        }
+    }
+
+    #[test]
+    fn walls_and_secret_doors_stop_the_search() {
+        let mut map = open_map();
+        for y in 1..11 {
+            let idx = map.xy_idx(5, y);
+            map.tiles[idx] = TileType::Wall;
+        }
+        let door_idx = map.xy_idx(5, 5);
+        map.tiles[door_idx] = TileType::SecretDoor;
+        let idx = map.xy_idx(4, 5);
+
+        let tiles = formation_tiles(&map, idx, 100, &HashSet::new());
+
+        assert!(!tiles.is_empty());
+        for tile in tiles {
+            assert!(tile as i32 % map.width < 5);
+        }
+    }
 }
@@ -0,0 +1,71 @@
+//! Logic for terrain effects on entities moving through or standing on it.
+
+use crate::{
+    CombatStats, Map, Name, Position, RunState, SufferDamage, TileType,
+    components::{EntityMoved, Slowed},
+    gamelog::GameLog,
+};
+use specs::prelude::*;
+
+/// Damage dealt each turn to anything standing in lava.
+const LAVA_DAMAGE: i32 = 5;
+
+/// System for applying terrain effects in an ECS.
+///
+/// Wading into shallow water costs a turn, and lava burns whatever stands in it once per turn.
+pub struct TerrainSystem {}
+
+impl<'a> System<'a> for TerrainSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        ReadStorage<'a, EntityMoved>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, Slowed>,
+        WriteStorage<'a, SufferDamage>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            map,
+            runstate,
+            mut log,
+            entities,
+            entity_moved,
+            positions,
+            combat_stats,
+            names,
+            mut slowed,
+            mut inflict_damage,
+        ) = data;
+
+        for (entity, _moved, pos) in (&entities, &entity_moved, &positions).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            if map.tiles[idx] == TileType::ShallowWater {
+                slowed
+                    .insert(entity, Slowed {})
+                    .expect("Unable to insert slowed");
+            }
+        }
+
+        // Systems run after both the player's and the monsters' moves, so only burn once a turn.
+        if *runstate != RunState::MonsterTurn {
+            return;
+        }
+        for (entity, pos, _stats) in (&entities, &positions, &combat_stats).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            if map.tiles[idx] == TileType::Lava {
+                SufferDamage::new_damage(&mut inflict_damage, entity, LAVA_DAMAGE);
+                if let Some(name) = names.get(entity) {
+                    log.entries
+                        .push(format!("{} is burned by the lava!", &name.name));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Logic for entry triggers, such as traps, firing when something steps onto their tile.
+
+use crate::{
+    Map, Monster, Name, Position, SufferDamage, TileType, Viewshed,
+    components::{
+        Alerted, EntityMoved, EntryTrigger, Hidden, InflictsDamage, RaisesAlarm, SingleActivation,
+        TeleportsEntity,
+    },
+    gamelog::GameLog,
+};
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+/// Number of turns monsters stay alerted to the player after an alarm.
+const ALARM_TURNS: i32 = 20;
+
+/// System for firing entry triggers in an ECS.
+///
+/// Looks up every entity that moved this turn in the map's tile index and fires any entry triggers sharing its tile.
+pub struct TriggerSystem {}
+
+impl<'a> System<'a> for TriggerSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        WriteExpect<'a, Point>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, EntityMoved>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, EntryTrigger>,
+        WriteStorage<'a, Hidden>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, InflictsDamage>,
+        WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, SingleActivation>,
+        ReadStorage<'a, TeleportsEntity>,
+        ReadStorage<'a, RaisesAlarm>,
+        ReadStorage<'a, Monster>,
+        WriteStorage<'a, Alerted>,
+        WriteStorage<'a, Viewshed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            map,
+            mut player_pos,
+            player_entity,
+            mut rng,
+            mut log,
+            entities,
+            mut entity_moved,
+            mut positions,
+            entry_triggers,
+            mut hidden,
+            names,
+            inflicts_damage,
+            mut inflict_damage,
+            single_activation,
+            teleports,
+            alarms,
+            monsters,
+            mut alerted,
+            mut viewsheds,
+        ) = data;
+
+        // Find every trigger sharing a tile with something that just moved onto it.
+        let mut fired: Vec<(Entity, Entity)> = Vec::new();
+        for (entity, _moved, pos) in (&entities, &entity_moved, &positions).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            for trigger in map.tile_content[idx].iter() {
+                if entity != *trigger && entry_triggers.get(*trigger).is_some() {
+                    fired.push((*trigger, entity));
+                }
+            }
+        }
+        entity_moved.clear();
+
+        let mut used_up: Vec<Entity> = Vec::new();
+        for (trigger, victim) in fired {
+            // Anything that fires is no longer a secret.
+            hidden.remove(trigger);
+
+            if let (Some(victim_name), Some(trigger_name)) = (names.get(victim), names.get(trigger))
+            {
+                log.entries.push(format!(
+                    "{} triggers {}!",
+                    &victim_name.name, &trigger_name.name
+                ));
+            }
+
+            if let Some(damage) = inflicts_damage.get(trigger) {
+                SufferDamage::new_damage(&mut inflict_damage, victim, damage.damage);
+            }
+
+            if teleports.get(trigger).is_some() {
+                let floor_tiles: Vec<usize> = map
+                    .tiles
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, tile)| **tile == TileType::Floor && !map.blocked[*idx])
+                    .map(|(idx, _)| idx)
+                    .collect();
+                if let Some(idx) = rng.random_slice_entry(&floor_tiles)
+                    && let Some(pos) = positions.get_mut(victim)
+                {
+                    pos.x = *idx as i32 % map.width;
+                    pos.y = *idx as i32 / map.width;
+                    if victim == *player_entity {
+                        *player_pos = Point::new(pos.x, pos.y);
+                    }
+                    if let Some(viewshed) = viewsheds.get_mut(victim) {
+                        viewshed.dirty = true;
+                    }
+                }
+            }
+
+            if alarms.get(trigger).is_some() {
+                log.entries
+                    .push("A piercing alarm echoes through the level!".to_string());
+                for (monster_entity, _monster, _pos) in (&entities, &monsters, &positions).join() {
+                    alerted
+                        .insert(monster_entity, Alerted { turns: ALARM_TURNS })
+                        .expect("Unable to insert alert");
+                }
+            }
+
+            if single_activation.get(trigger).is_some() && !used_up.contains(&trigger) {
+                used_up.push(trigger);
+            }
+        }
+
+        for trigger in used_up {
+            entities.delete(trigger).expect("Unable to delete trigger");
+        }
+    }
+}
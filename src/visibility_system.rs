@@ -2,10 +2,13 @@
 //!
 //! Handles what on the map should be visible.
 
-use crate::{Map, Player, Position, Viewshed};
-use rltk::{Point, field_of_view};
+use crate::{Map, Name, Player, Position, Viewshed, components::Hidden, gamelog::GameLog};
+use rltk::{Point, RandomNumberGenerator, field_of_view};
 use specs::prelude::*;
 
+/// The player has a one in this many chance of spotting each hidden thing in view, every time their view changes.
+const SPOT_HIDDEN_CHANCE: i32 = 10;
+
 pub struct VisibilitySystem {}
 
 impl<'a> System<'a> for VisibilitySystem {
@@ -15,10 +18,15 @@ impl<'a> System<'a> for VisibilitySystem {
         WriteStorage<'a, Viewshed>,
         WriteStorage<'a, Position>,
         ReadStorage<'a, Player>,
+        WriteStorage<'a, Hidden>,
+        ReadStorage<'a, Name>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, GameLog>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, entities, mut viewshed, pos, player) = data;
+        let (mut map, entities, mut viewshed, pos, player, mut hidden, names, mut rng, mut log) =
+            data;
 
         for (ent, viewshed, pos) in (&entities, &mut viewshed, &pos).join() {
             if viewshed.dirty {
@@ -45,6 +53,17 @@ impl<'a> System<'a> for VisibilitySystem {
                         let idx = map.xy_idx(vis.x, vis.y);
                         map.revealed_tiles[idx] = true;
                         map.visible_tiles[idx] = true;
+
+                        // Perception roll to spot anything hidden on the tile.
+                        for e in map.tile_content[idx].iter() {
+                            if hidden.get(*e).is_some() && rng.roll_dice(1, SPOT_HIDDEN_CHANCE) == 1
+                            {
+                                if let Some(name) = names.get(*e) {
+                                    log.entries.push(format!("You spotted a {}.", &name.name));
+                                }
+                                hidden.remove(*e);
+                            }
+                        }
                     }
                 }
             }